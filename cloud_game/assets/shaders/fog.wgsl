@@ -27,65 +27,66 @@ fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
         // Transparent
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     } else {
-        // Add transparency for all hexling positions
+        // Add transparency for all hexling positions. Each one's z is how far it sees, relative to the
+        // usual distance; empty slots are all zero, so see nothing.
         var hex_pos = position_world_to_ndc(hexling_a).xy * view.viewport.zw + view.viewport.xz;
         var hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_a.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_b).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_b.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_c).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_c.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_d).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_d.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_e).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_e.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_f).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_f.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_g).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_g.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_h).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_h.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_i).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_i.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_j).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_j.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_k).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_k.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
         hex_pos = position_world_to_ndc(hexling_l).xy * view.viewport.zw + view.viewport.xz;
         hd = distance(mesh_pos, hex_pos);
-        if hd < (light_radius / 1.5) {
+        if hd < (light_radius / 1.5) * hexling_l.z {
             return vec4<f32>(0.0, 0.0, 0.0, 0.0);
         }
     }
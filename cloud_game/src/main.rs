@@ -29,7 +29,29 @@ fn main() {
         .add_plugins(cloud_lib::collision::CollisionPlugin)
         .add_plugins(cloud_lib::movement::MovementPlugin)
         .add_plugins(cloud_lib::map::MapPlugin)
+        .add_plugins(cloud_lib::navigation::NavigationPlugin)
         .add_plugins(cloud_lib::hexling::HexlingPlugin)
         .add_plugins(cloud_lib::enemy::EnemyPlugin)
+        .add_plugins(cloud_lib::swarm::SwarmPlugin)
+        .add_plugins(cloud_lib::nest::NestPlugin)
+        .add_plugins(cloud_lib::food::FoodPlugin)
+        .add_plugins(cloud_lib::status::StatusPlugin)
+        .add_plugins(cloud_lib::combat::CombatPlugin)
+        .add_plugins(cloud_lib::damage::DamagePlugin)
+        .add_plugins(cloud_lib::modifiers::ModifierPlugin)
+        .add_plugins(cloud_lib::feedback::FeedbackPlugin)
+        .add_plugins(cloud_lib::ability::AbilityPlugin)
+        .add_plugins(cloud_lib::steering::SteeringPlugin)
+        .add_plugins(cloud_lib::aim::AimPlugin)
+        .add_plugins(cloud_lib::orders::OrdersPlugin)
+        .add_plugins(cloud_lib::groups::GroupsPlugin)
+        .add_plugins(cloud_lib::economy::EconomyPlugin)
+        .add_plugins(cloud_lib::classes::ClassPlugin)
+        .add_plugins(cloud_lib::upgrades::UpgradePlugin)
+        .add_plugins(cloud_lib::story::StoryPlugin)
+        .add_plugins(cloud_lib::veterancy::VeterancyPlugin)
+        .add_plugins(cloud_lib::sacrifice::SacrificePlugin)
+        .add_plugins(cloud_lib::particles::ParticlePlugin)
+        .add_plugins(cloud_lib::shapes::ShapePlugin)
         .run();
 }
@@ -0,0 +1,420 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+};
+
+use crate::{
+    enemy::Enemy,
+    hexling::Hexling,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    movement::Velocity,
+    player::{Player, Recovery},
+    shapes::ShapeCache,
+    sound::SoundSettings,
+    GameState,
+};
+
+const PULSE_COLOR: Color = Color::rgba(0.6, 1.4, 0.4, 0.4);
+// Enough sides to pass for a circle at full size.
+const PULSE_SIDES: usize = 64;
+const PULSE_WAVE_SECONDS: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy)]
+pub enum AbilityEffect {
+    // A burst of speed in the direction of travel, invulnerable for the duration, so the caster
+    // slips straight through enemies.
+    Dash {
+        impulse: f32,
+        invulnerable_seconds: f32,
+    },
+    // Shoves enemies away from the caster, and spurs hexlings on for a while.
+    Pulse {
+        radius: f32,
+        knockback: f32,
+        hexling_speed: f32,
+        boost_seconds: f32,
+    },
+}
+
+#[derive(Debug)]
+pub struct Ability {
+    pub name: &'static str,
+    pub cooldown_seconds: f32,
+    pub effect: AbilityEffect,
+    pub key: KeyCode,
+    pub sound: &'static str,
+}
+
+pub const DASH: Ability = Ability {
+    name: "dash",
+    cooldown_seconds: 1.5,
+    effect: AbilityEffect::Dash {
+        impulse: 900.,
+        invulnerable_seconds: 0.3,
+    },
+    key: KeyCode::E,
+    sound: "audio/g.ogg",
+};
+
+pub const PULSE: Ability = Ability {
+    name: "pulse",
+    cooldown_seconds: 6.,
+    effect: AbilityEffect::Pulse {
+        radius: 150.,
+        knockback: 400.,
+        hexling_speed: 1.5,
+        boost_seconds: 3.,
+    },
+    key: KeyCode::F,
+    sound: "audio/b.ogg",
+};
+
+struct AbilitySlot {
+    ability: &'static Ability,
+    // Seconds until the ability can be used again.
+    cooldown: f32,
+}
+
+#[derive(Component)]
+pub struct Abilities(Vec<AbilitySlot>);
+
+impl Abilities {
+    pub fn new(abilities: &[&'static Ability]) -> Self {
+        Self(
+            abilities
+                .iter()
+                .map(|ability| AbilitySlot {
+                    ability,
+                    cooldown: 0.,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Event)]
+pub struct AbilityEvent {
+    pub caster: Entity,
+    pub ability: &'static Ability,
+}
+
+#[derive(Component)]
+struct PulseWave {
+    radius: f32,
+    timer: f32,
+}
+
+pub struct AbilityPlugin;
+
+impl Plugin for AbilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AbilityEvent>().add_systems(
+            Update,
+            (use_abilities, (dash, pulse), expand_pulse_waves)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn use_abilities(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    mut ev_ability: EventWriter<AbilityEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(Entity, &mut Abilities), With<Player>>,
+    sound_settings: Res<SoundSettings>,
+    time: Res<Time>,
+) {
+    for (entity, mut abilities) in query.iter_mut() {
+        for slot in abilities.0.iter_mut() {
+            slot.cooldown -= time.delta_seconds();
+            if slot.cooldown > 0. || !keyboard_input.just_pressed(slot.ability.key) {
+                continue;
+            }
+            slot.cooldown = slot.ability.cooldown_seconds;
+            ev_ability.send(AbilityEvent {
+                caster: entity,
+                ability: slot.ability,
+            });
+            commands.spawn((AudioBundle {
+                source: asset_server.load(slot.ability.sound),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Once,
+                    volume: Volume::new_relative(sound_settings.effects_volume),
+                    ..default()
+                },
+            },));
+        }
+    }
+}
+
+fn dash(
+    mut ev_ability: EventReader<AbilityEvent>,
+    mut query: Query<(&mut Velocity, Option<&mut Recovery>)>,
+) {
+    for ev in ev_ability.read() {
+        let AbilityEffect::Dash {
+            impulse,
+            invulnerable_seconds,
+        } = ev.ability.effect
+        else {
+            continue;
+        };
+        let Ok((mut velocity, recovery)) = query.get_mut(ev.caster) else {
+            continue;
+        };
+        // Standing still, dash upward rather than not at all.
+        let direction = match velocity.value.normalize_or_zero() {
+            Vec3::ZERO => Vec3::Y,
+            direction => direction,
+        };
+        velocity.impulse += direction * impulse;
+        if let Some(mut recovery) = recovery {
+            recovery.make_invulnerable(invulnerable_seconds);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pulse(
+    mut commands: Commands,
+    mut enemy_query: Query<(&Transform, &mut Velocity), With<Enemy>>,
+    mut ev_ability: EventReader<AbilityEvent>,
+    mut hexling_query: Query<&mut StatModifiers, With<Hexling>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    caster_query: Query<&Transform, Without<Enemy>>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    for ev in ev_ability.read() {
+        let AbilityEffect::Pulse {
+            radius,
+            knockback,
+            hexling_speed,
+            boost_seconds,
+        } = ev.ability.effect
+        else {
+            continue;
+        };
+        let Ok(caster) = caster_query.get(ev.caster) else {
+            continue;
+        };
+
+        for (transform, mut velocity) in enemy_query.iter_mut() {
+            let away = (transform.translation - caster.translation).truncate();
+            if away.length() < radius {
+                velocity.impulse += away.normalize_or_zero().extend(0.) * knockback;
+            }
+        }
+        for mut modifiers in hexling_query.iter_mut() {
+            modifiers.add(
+                StatModifier::new(
+                    Stat::MoveSpeed,
+                    ModifierOp::Mul(hexling_speed),
+                    ev.ability.name,
+                )
+                .timed(boost_seconds),
+            );
+        }
+
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: shapes.mesh(&mut meshes, PULSE_SIDES, 1.),
+                // Its own material, as each wave fades on its own.
+                material: materials.add(ColorMaterial::from(PULSE_COLOR)),
+                transform: Transform::from_translation(caster.translation.truncate().extend(-0.5)),
+                ..default()
+            },
+            Name::new("pulse wave"),
+            PulseWave { radius, timer: 0. },
+        ));
+    }
+}
+
+// The wave races out to the pulse's radius, fading as it goes.
+fn expand_pulse_waves(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(
+        Entity,
+        &Handle<ColorMaterial>,
+        &mut PulseWave,
+        &mut Transform,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, handle, mut wave, mut transform) in query.iter_mut() {
+        wave.timer += time.delta_seconds();
+        let progress = wave.timer / PULSE_WAVE_SECONDS;
+        if progress >= 1. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.scale = Vec3::new(wave.radius * progress, wave.radius * progress, 1.);
+        if let Some(material) = materials.get_mut(handle) {
+            material.color.set_a(PULSE_COLOR.a() * (1. - progress));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{audio::AudioSource, ecs::event::ManualEventReader, utils::Duration};
+
+    use super::*;
+    use crate::{
+        combat::Health,
+        damage::{apply_damage, DamageEvent, DamageKind, HitEvent},
+        player::{player_recovery, RecoverySettings},
+    };
+
+    fn fired(app: &App, reader: &mut ManualEventReader<AbilityEvent>) -> Vec<&'static str> {
+        reader
+            .read(app.world.resource::<Events<AbilityEvent>>())
+            .map(|ev| ev.ability.name)
+            .collect()
+    }
+
+    fn press(app: &mut App, key: KeyCode, seconds: f32) {
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.clear();
+        input.release_all();
+        input.press(key);
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn cooldowns_gate_each_ability() {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .add_event::<AbilityEvent>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Time>()
+            .insert_resource(SoundSettings {
+                effects_on: true,
+                effects_volume: 0.5,
+                global_sound_on: true,
+                global_volume_db: 1.,
+                soundtrack_on: true,
+                soundtrack_volume: 1.,
+            })
+            .add_systems(Update, use_abilities);
+        app.world.spawn((Abilities::new(&[&DASH, &PULSE]), Player));
+        let mut reader = ManualEventReader::default();
+
+        press(&mut app, DASH.key, 0.);
+        assert_eq!(fired(&app, &mut reader), ["dash"]);
+        // Still cooling down.
+        press(&mut app, DASH.key, DASH.cooldown_seconds / 2.);
+        assert!(fired(&app, &mut reader).is_empty());
+        // Each ability keeps its own cooldown.
+        press(&mut app, PULSE.key, 0.);
+        assert_eq!(fired(&app, &mut reader), ["pulse"]);
+        press(&mut app, DASH.key, DASH.cooldown_seconds / 2.);
+        assert_eq!(fired(&app, &mut reader), ["dash"]);
+        press(&mut app, PULSE.key, 0.);
+        assert!(fired(&app, &mut reader).is_empty());
+    }
+
+    #[test]
+    fn dash_slips_through_enemies() {
+        let mut app = App::new();
+        app.add_event::<AbilityEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<HitEvent>()
+            .init_resource::<RecoverySettings>()
+            .init_resource::<Time>()
+            .add_systems(Update, (dash, player_recovery, apply_damage).chain());
+        let player = app
+            .world
+            .spawn((
+                Health::new(10.),
+                Player,
+                Recovery::default(),
+                Transform::default(),
+                Velocity::new(Vec3::X * 10.),
+                Visibility::default(),
+            ))
+            .id();
+        let enemy = app.world.spawn_empty().id();
+
+        app.world.send_event(AbilityEvent {
+            caster: player,
+            ability: &DASH,
+        });
+        app.world.send_event(DamageEvent {
+            target: player,
+            source: Some(enemy),
+            amount: 1.,
+            kind: DamageKind::Kinetic,
+        });
+        app.update();
+
+        let AbilityEffect::Dash { impulse, .. } = DASH.effect else {
+            unreachable!();
+        };
+        assert_eq!(
+            app.world.get::<Velocity>(player).unwrap().impulse,
+            Vec3::X * impulse
+        );
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 10.);
+    }
+
+    #[test]
+    fn pulse_shoves_enemies_and_spurs_hexlings() {
+        let mut app = App::new();
+        app.init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<ShapeCache>()
+            .add_event::<AbilityEvent>()
+            .add_systems(Update, pulse);
+        let AbilityEffect::Pulse {
+            radius,
+            hexling_speed,
+            ..
+        } = PULSE.effect
+        else {
+            unreachable!();
+        };
+        let caster = app.world.spawn(Transform::default()).id();
+        let near = app
+            .world
+            .spawn((
+                Enemy,
+                Transform::from_xyz(radius / 2., 0., 0.),
+                Velocity::new(Vec3::ZERO),
+            ))
+            .id();
+        let far = app
+            .world
+            .spawn((
+                Enemy,
+                Transform::from_xyz(radius * 2., 0., 0.),
+                Velocity::new(Vec3::ZERO),
+            ))
+            .id();
+        let hexling = app.world.spawn((Hexling, StatModifiers::default())).id();
+
+        for _ in 0..2 {
+            app.world.send_event(AbilityEvent {
+                caster,
+                ability: &PULSE,
+            });
+            app.update();
+        }
+
+        assert!(app.world.get::<Velocity>(near).unwrap().impulse.x > 0.);
+        assert_eq!(app.world.get::<Velocity>(far).unwrap().impulse, Vec3::ZERO);
+        let modifiers = app.world.get::<StatModifiers>(hexling).unwrap();
+        assert_eq!(modifiers.apply(Stat::MoveSpeed, 1.), hexling_speed);
+        assert_eq!(app.world.query::<&PulseWave>().iter(&app.world).count(), 2);
+        // Both waves share a mesh.
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 1);
+    }
+}
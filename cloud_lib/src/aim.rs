@@ -0,0 +1,319 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+
+use crate::{enemy::Enemy, player::Player, GameState};
+
+// How far ahead of the player a fully tilted right stick aims.
+const AIM_DISTANCE: f32 = 250.;
+// Locking picks the enemy closest to the aim point, if any is this close.
+const LOCK_RADIUS: f32 = 100.;
+const LOCKED_COLOR: Color = Color::rgb(2.5, 0.4, 0.3);
+const RETICLE_COLOR: Color = Color::rgba(1.5, 1.5, 1.2, 0.6);
+const RETICLE_RADIUS: f32 = 10.;
+const STICK_DEADZONE: f32 = 0.2;
+
+// Where a charge is headed. Follows whichever of the mouse and right stick was used last, unless a
+// target is locked, in which case it follows the target.
+#[derive(Default, Resource)]
+pub struct AimPoint {
+    pub point: Option<Vec3>,
+    pub locked: Option<Entity>,
+    source: AimSource,
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+enum AimSource {
+    #[default]
+    Mouse,
+    Stick(Gamepad),
+}
+
+#[derive(Component)]
+pub struct Reticle;
+
+pub struct AimPlugin;
+
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AimPoint>()
+            .add_systems(Startup, spawn_reticle)
+            .add_systems(OnEnter(GameState::Over), hide_reticle)
+            .add_systems(
+                Update,
+                (update_aim, lock_target, move_reticle)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_reticle(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(shape::RegularPolygon::new(RETICLE_RADIUS, 4).into())
+                .into(),
+            material: materials.add(ColorMaterial::from(RETICLE_COLOR)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Name::new("reticle"),
+        Reticle,
+    ));
+}
+
+fn hide_reticle(mut aim: ResMut<AimPoint>, mut query: Query<&mut Visibility, With<Reticle>>) {
+    *aim = AimPoint::default();
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn update_aim(
+    mut aim: ResMut<AimPoint>,
+    axes: Res<Axis<GamepadAxis>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut ev_cursor: EventReader<CursorMoved>,
+    gamepads: Res<Gamepads>,
+    player_query: Query<&Transform, With<Player>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    if ev_cursor.read().count() > 0 {
+        aim.source = AimSource::Mouse;
+    }
+    for gamepad in gamepads.iter() {
+        let stick = right_stick(&axes, gamepad);
+        if stick.length() > STICK_DEADZONE {
+            aim.source = AimSource::Stick(gamepad);
+        }
+    }
+
+    let point = match aim.source {
+        // The cursor stays put on screen while the camera moves, so re-project it every frame.
+        AimSource::Mouse => window_query
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .and_then(|cursor| {
+                let (camera, camera_transform) = camera_query.get_single().ok()?;
+                camera.viewport_to_world_2d(camera_transform, cursor)
+            })
+            .map(|point| point.extend(0.)),
+        AimSource::Stick(gamepad) => player_query.get_single().ok().map(|player| {
+            let stick = right_stick(&axes, gamepad);
+            player.translation + stick.clamp_length_max(1.).extend(0.) * AIM_DISTANCE
+        }),
+    };
+    // Hang on to the last good point if the cursor leaves the window.
+    if point.is_some() {
+        aim.point = point;
+    }
+}
+
+fn lock_target(
+    mut aim: ResMut<AimPoint>,
+    buttons: Res<Input<GamepadButton>>,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    gamepads: Res<Gamepads>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    let toggled = keyboard_input.just_pressed(KeyCode::Tab)
+        || gamepads.iter().any(|gamepad| {
+            buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::RightThumb))
+        });
+    if toggled {
+        aim.locked = match (aim.locked, aim.point) {
+            (None, Some(point)) => enemy_query
+                .iter()
+                .map(|(entity, transform)| (entity, (transform.translation - point).length()))
+                .filter(|(_, distance)| *distance < LOCK_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(entity, _)| entity),
+            _ => None,
+        };
+    }
+
+    if let Some(locked) = aim.locked {
+        match enemy_query.get(locked) {
+            Ok((_, transform)) => aim.point = Some(transform.translation),
+            // Target destroyed: back to free aim.
+            Err(_) => aim.locked = None,
+        }
+    }
+}
+
+fn move_reticle(
+    aim: Res<AimPoint>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&Handle<ColorMaterial>, &mut Transform, &mut Visibility), With<Reticle>>,
+    time: Res<Time>,
+) {
+    let Ok((handle, mut transform, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let Some(point) = aim.point else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+    transform.translation = point.truncate().extend(5.);
+    transform.rotate_z(time.delta_seconds());
+
+    let color = if aim.locked.is_some() {
+        LOCKED_COLOR
+    } else {
+        RETICLE_COLOR
+    };
+    // Only touch the asset when the colour actually changes, to avoid re-uploading it.
+    if materials.get(handle).is_some_and(|m| m.color != color) {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    }
+}
+
+fn right_stick(axes: &Axis<GamepadAxis>, gamepad: Gamepad) -> Vec2 {
+    Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, GamepadAxisType::RightStickX))
+            .unwrap_or(0.),
+        axes.get(GamepadAxis::new(gamepad, GamepadAxisType::RightStickY))
+            .unwrap_or(0.),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        input::gamepad::{
+            gamepad_connection_system, GamepadConnection, GamepadConnectionEvent, GamepadInfo,
+        },
+        render::{
+            camera::{camera_system, ManualTextureViews},
+            texture::Image,
+        },
+        window::{WindowCreated, WindowResized},
+    };
+
+    use super::*;
+
+    const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+    // A window with a camera in the middle of it, a player and a connected gamepad.
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<AimPoint>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Axis<GamepadButton>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<AssetEvent<Image>>()
+            .add_event::<CursorMoved>()
+            .add_event::<GamepadConnectionEvent>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>()
+            .add_systems(
+                Update,
+                (
+                    gamepad_connection_system,
+                    camera_system::<OrthographicProjection>,
+                    update_aim,
+                    lock_target,
+                )
+                    .chain(),
+            );
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        app.world.spawn(Camera2dBundle::default());
+        app.world.spawn((Player, Transform::default()));
+        app.world.send_event(GamepadConnectionEvent {
+            gamepad: GAMEPAD,
+            connection: GamepadConnection::Connected(GamepadInfo {
+                name: "pad".to_string(),
+            }),
+        });
+        app.update();
+        (app, window)
+    }
+
+    fn tilt(app: &mut App, x: f32, y: f32) {
+        let mut axes = app.world.resource_mut::<Axis<GamepadAxis>>();
+        axes.set(GamepadAxis::new(GAMEPAD, GamepadAxisType::RightStickX), x);
+        axes.set(GamepadAxis::new(GAMEPAD, GamepadAxisType::RightStickY), y);
+    }
+
+    fn aim(app: &App) -> &AimPoint {
+        app.world.resource::<AimPoint>()
+    }
+
+    // Projecting the cursor through the camera isn't exact.
+    fn aimed_near(app: &App, point: Vec3) -> bool {
+        aim(app).point.is_some_and(|p| p.abs_diff_eq(point, 0.01))
+    }
+
+    #[test]
+    fn aim_follows_whichever_was_used_last() {
+        let (mut app, window) = app();
+        let centre = app
+            .world
+            .get::<Window>(window)
+            .map(|w| Vec2::new(w.width(), w.height()) / 2.)
+            .unwrap();
+        // Screen y points down, world y up.
+        let cursor = centre + Vec2::new(100., -50.);
+        app.world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .set_cursor_position(Some(cursor));
+        app.update();
+        assert!(aimed_near(&app, Vec3::new(100., 50., 0.)));
+
+        tilt(&mut app, 0., 1.);
+        app.update();
+        assert_eq!(aim(&app).point, Some(Vec3::new(0., AIM_DISTANCE, 0.)));
+
+        // Let go of the stick and the mouse is back in charge once it moves.
+        tilt(&mut app, 0., 0.);
+        app.world.send_event(CursorMoved {
+            window,
+            position: cursor,
+        });
+        app.update();
+        assert!(aimed_near(&app, Vec3::new(100., 50., 0.)));
+    }
+
+    #[test]
+    fn locking_holds_the_aim_on_a_target() {
+        let (mut app, _) = app();
+        let enemy = app
+            .world
+            .spawn((Enemy, Transform::from_xyz(AIM_DISTANCE + 10., 0., 0.)))
+            .id();
+        tilt(&mut app, 1., 0.);
+        app.update();
+
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(GamepadButton::new(GAMEPAD, GamepadButtonType::RightThumb));
+        app.update();
+        app.world.resource_mut::<Input<GamepadButton>>().clear();
+        assert_eq!(aim(&app).locked, Some(enemy));
+
+        // Aiming elsewhere doesn't shake it, but the target moving does move it.
+        tilt(&mut app, 0., 1.);
+        app.world.get_mut::<Transform>(enemy).unwrap().translation = Vec3::new(0., -100., 0.);
+        app.update();
+        assert_eq!(aim(&app).point, Some(Vec3::new(0., -100., 0.)));
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Tab);
+        app.update();
+        assert_eq!(aim(&app).locked, None);
+        assert_eq!(aim(&app).point, Some(Vec3::new(0., AIM_DISTANCE, 0.)));
+    }
+}
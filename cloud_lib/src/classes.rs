@@ -0,0 +1,436 @@
+use bevy::prelude::*;
+
+use crate::{
+    combat::Targeting,
+    economy::Stockpile,
+    food::Food,
+    hexling::Hexling,
+    orders::Unordered,
+    player::{SpawnQueue, SpawnSettings},
+    steering::Boid,
+    upgrades::Upgrades,
+    GameState,
+};
+
+const BAR_COLOR: Color = Color::rgba(0.4, 1., 0.5, 0.8);
+// A full bar that can't be spent yet, for want of matter or room.
+const BAR_WAITING_COLOR: Color = Color::rgba(1., 0.4, 0.3, 0.8);
+const BAR_WIDTH: f32 = 120.;
+const HUD_COLOR: Color = Color::rgba(1., 1., 1., 0.5);
+const HUD_SIZE: f32 = 18.;
+
+#[derive(Component, Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum HexlingClass {
+    Scout,
+    #[default]
+    Striker,
+    Guardian,
+    Harvester,
+}
+
+impl HexlingClass {
+    const ALL: [HexlingClass; 4] = [
+        HexlingClass::Scout,
+        HexlingClass::Striker,
+        HexlingClass::Guardian,
+        HexlingClass::Harvester,
+    ];
+
+    pub fn stats(self) -> &'static ClassStats {
+        match self {
+            HexlingClass::Scout => &SCOUT,
+            HexlingClass::Striker => &STRIKER,
+            HexlingClass::Guardian => &GUARDIAN,
+            HexlingClass::Harvester => &HARVESTER,
+        }
+    }
+
+    fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug)]
+pub struct ClassStats {
+    pub name: &'static str,
+    // Vision: how far away the hexling notices enemies (and, for harvesters, food).
+    pub aggro_radius: f32,
+    pub attack_range: f32,
+    pub base_damage: f32,
+    // Matter spent to spawn one.
+    pub cost: f32,
+    pub health: f32,
+    // Guardians within this distance of the player take hits meant for them.
+    pub intercept_radius: Option<f32>,
+    // A multiplier on hexling speed.
+    pub move_speed: f32,
+    pub radius: f32,
+    // A multiplier on how much fog the hexling clears around itself.
+    pub reveal: f32,
+}
+
+// Quick, far-sighted and flimsy. Clears fog well ahead of the swarm.
+pub const SCOUT: ClassStats = ClassStats {
+    name: "scout",
+    aggro_radius: 120.,
+    attack_range: 10.,
+    base_damage: 0.5,
+    cost: 2.,
+    health: 6.,
+    intercept_radius: None,
+    move_speed: 1.4,
+    radius: 5.,
+    reveal: 1.6,
+};
+
+pub const STRIKER: ClassStats = ClassStats {
+    name: "striker",
+    aggro_radius: 50.,
+    attack_range: 10.,
+    base_damage: 2.,
+    cost: 3.,
+    health: 10.,
+    intercept_radius: None,
+    move_speed: 1.,
+    radius: 6.,
+    reveal: 1.,
+};
+
+// Slow and sturdy, and throws itself in front of anything aimed at the player.
+pub const GUARDIAN: ClassStats = ClassStats {
+    name: "guardian",
+    aggro_radius: 40.,
+    attack_range: 10.,
+    base_damage: 0.5,
+    cost: 5.,
+    health: 24.,
+    intercept_radius: Some(90.),
+    move_speed: 0.8,
+    radius: 8.,
+    reveal: 0.8,
+};
+
+// Gathers food into matter rather than eating it, and would rather not fight.
+pub const HARVESTER: ClassStats = ClassStats {
+    name: "harvester",
+    aggro_radius: 90.,
+    attack_range: 10.,
+    base_damage: 0.25,
+    cost: 3.,
+    health: 8.,
+    intercept_radius: None,
+    move_speed: 1.1,
+    radius: 6.,
+    reveal: 1.,
+};
+
+// The class the next spawned hexling will be. Q cycles through them.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnClass(pub HexlingClass);
+
+#[derive(Component)]
+pub struct ClassHud;
+
+// Fills as Left Shift is held towards the next spawn.
+#[derive(Component)]
+pub struct SpawnBar;
+
+pub struct ClassPlugin;
+
+impl Plugin for ClassPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnClass>()
+            .add_systems(OnEnter(GameState::Playing), spawn_hud.run_if(run_once()))
+            .add_systems(
+                Update,
+                (
+                    choose_class,
+                    harvest
+                        .after(crate::hexling::hexling_charge)
+                        .after(crate::hexling::hexling_recall)
+                        .before(crate::steering::flock),
+                    update_hud,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        ClassHud,
+        Name::new("class hud"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: HUD_COLOR,
+                font_size: HUD_SIZE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            left: Val::Px(12.),
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.),
+            ..default()
+        }),
+    ));
+    commands
+        .spawn((
+            Name::new("spawn bar"),
+            NodeBundle {
+                background_color: BackgroundColor(Color::rgba(1., 1., 1., 0.1)),
+                style: Style {
+                    height: Val::Px(4.),
+                    left: Val::Px(12.),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(54.),
+                    width: Val::Px(BAR_WIDTH),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                NodeBundle {
+                    background_color: BackgroundColor(BAR_COLOR),
+                    style: Style {
+                        height: Val::Percent(100.),
+                        width: Val::Percent(0.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                SpawnBar,
+            ));
+        });
+}
+
+fn choose_class(keyboard_input: Res<Input<KeyCode>>, mut spawn_class: ResMut<SpawnClass>) {
+    if keyboard_input.just_pressed(KeyCode::Q) {
+        spawn_class.0 = spawn_class.0.next();
+    }
+}
+
+// Harvesters with nothing to fight go after the nearest food they can see.
+#[allow(clippy::type_complexity)]
+fn harvest(
+    food_query: Query<&Transform, With<Food>>,
+    mut query: Query<
+        (&HexlingClass, &mut Boid, &Targeting, &Transform),
+        (With<Hexling>, Unordered),
+    >,
+) {
+    for (class, mut boid, targeting, transform) in query.iter_mut() {
+        if *class != HexlingClass::Harvester || targeting.primary().is_some() {
+            continue;
+        }
+        let nearest = food_query
+            .iter()
+            .map(|food| food.translation)
+            .filter(|food| (*food - transform.translation).length() < targeting.aggro_radius)
+            .min_by(|a, b| {
+                let a = (*a - transform.translation).length_squared();
+                let b = (*b - transform.translation).length_squared();
+                a.total_cmp(&b)
+            });
+        if let Some(food) = nearest {
+            boid.seek(food - transform.translation, food, 0.);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_hud(
+    mut bar_query: Query<(&mut BackgroundColor, &mut Style), With<SpawnBar>>,
+    hexling_query: Query<(), With<Hexling>>,
+    mut query: Query<&mut Text, With<ClassHud>>,
+    settings: Res<SpawnSettings>,
+    spawn_class: Res<SpawnClass>,
+    spawn_queue: Res<SpawnQueue>,
+    stockpile: Res<Stockpile>,
+    upgrades: Res<Upgrades>,
+) {
+    if let Ok(mut text) = query.get_single_mut() {
+        let stats = spawn_class.0.stats();
+        let queued = match spawn_queue.queued.len() {
+            0 => String::new(),
+            n => format!(" (+{})", n),
+        };
+        let value = format!(
+            "next: {} for {} (q)   matter: {}   hexlings: {}{}/{}",
+            stats.name,
+            stats.cost,
+            stockpile.matter.floor(),
+            hexling_query.iter().count(),
+            queued,
+            settings.population_cap(&upgrades),
+        );
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    let Ok((mut color, mut style)) = bar_query.get_single_mut() else {
+        return;
+    };
+    let progress = (spawn_queue.held / settings.hold_seconds).clamp(0., 1.);
+    let width = Val::Percent(progress * 100.);
+    if style.width != width {
+        style.width = width;
+    }
+    let fill = if progress >= 1. {
+        BAR_WAITING_COLOR
+    } else {
+        BAR_COLOR
+    };
+    if color.0 != fill {
+        color.0 = fill;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_rand::prelude::*;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        collision::Collider,
+        combat::{Attacker, Health},
+        damage::Interceptor,
+        fog::{FogMaterial, HexlingFogTracker},
+        food::feed_hexlings,
+        hexling::hexling_spawner,
+        map::Source,
+        player::{events::SpawnHexlingEvent, Player},
+        shapes::ShapeCache,
+        sound::SoundSettings,
+    };
+
+    fn sound_settings() -> SoundSettings {
+        SoundSettings {
+            effects_on: true,
+            effects_volume: 0.5,
+            global_sound_on: true,
+            global_volume_db: 1.,
+            soundtrack_on: true,
+            soundtrack_volume: 1.,
+        }
+    }
+
+    #[test]
+    fn q_cycles_through_the_classes() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<SpawnClass>()
+            .add_systems(Update, choose_class);
+        let mut seen = Vec::new();
+        for _ in 0..HexlingClass::ALL.len() {
+            app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Q);
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().reset_all();
+            seen.push(app.world.resource::<SpawnClass>().0);
+        }
+
+        assert_eq!(
+            seen,
+            vec![
+                HexlingClass::Guardian,
+                HexlingClass::Harvester,
+                HexlingClass::Scout,
+                HexlingClass::Striker,
+            ]
+        );
+    }
+
+    #[test]
+    fn hexlings_hatch_with_their_class_stats() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .init_asset::<ColorMaterial>()
+            .init_asset::<FogMaterial>()
+            .init_asset::<Mesh>()
+            .add_event::<SpawnHexlingEvent>()
+            .init_resource::<HexlingFogTracker>()
+            .init_resource::<ShapeCache>()
+            .insert_resource(sound_settings())
+            .add_systems(Startup, crate::fog::init)
+            .add_systems(Update, hexling_spawner);
+        app.world
+            .spawn((EntropyComponent::<ChaCha8Rng>::from_seed([1; 32]), Source));
+        let player = app.world.spawn((Player, Transform::default())).id();
+
+        for class in [HexlingClass::Guardian, HexlingClass::Scout] {
+            app.world.send_event(SpawnHexlingEvent(player, class));
+            app.update();
+        }
+
+        let mut hatched = app.world.query::<(
+            &HexlingClass,
+            &Attacker,
+            &Collider,
+            &Health,
+            &Targeting,
+            Option<&Interceptor>,
+        )>();
+        assert_eq!(hatched.iter(&app.world).count(), 2);
+        for (class, attacker, collider, health, targeting, interceptor) in hatched.iter(&app.world)
+        {
+            let stats = class.stats();
+            assert_eq!(attacker.base_damage, stats.base_damage);
+            assert_eq!(collider.radius, stats.radius);
+            assert_eq!(health.max, stats.health);
+            assert_eq!(targeting.aggro_radius, stats.aggro_radius);
+            assert_eq!(
+                interceptor.map(|interceptor| interceptor.radius),
+                stats.intercept_radius
+            );
+        }
+    }
+
+    #[test]
+    fn harvesters_bring_food_home() {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .init_resource::<Stockpile>()
+            .insert_resource(sound_settings())
+            .add_systems(Update, (harvest, feed_hexlings));
+        let food = Vec3::new(50., 0., 0.);
+        app.world
+            .spawn((Food { nourishment: 3. }, Transform::from_translation(food)));
+        let spawn_hexling = |app: &mut App, class: HexlingClass| {
+            app.world
+                .spawn((
+                    Boid::default(),
+                    class,
+                    Health::new(class.stats().health),
+                    Hexling,
+                    Targeting::new(class.stats().aggro_radius),
+                    Transform::default(),
+                ))
+                .id()
+        };
+        let harvester = spawn_hexling(&mut app, HexlingClass::Harvester);
+        let striker = spawn_hexling(&mut app, HexlingClass::Striker);
+        let matter = app.world.resource::<Stockpile>().matter;
+        app.update();
+
+        // Only the harvester goes after it.
+        assert_eq!(app.world.get::<Boid>(harvester).unwrap().goal, Some(food));
+        assert_eq!(app.world.get::<Boid>(striker).unwrap().goal, None);
+
+        // Once there, it's matter rather than a meal.
+        app.world
+            .get_mut::<Transform>(harvester)
+            .unwrap()
+            .translation = food;
+        app.update();
+        assert!(app.world.resource::<Stockpile>().matter > matter);
+        assert_eq!(app.world.query::<&Food>().iter(&app.world).count(), 0);
+    }
+}
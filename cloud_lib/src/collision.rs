@@ -1,6 +1,5 @@
 use bevy::{prelude::*, render::primitives::Aabb, sprite::collide_aabb, utils::HashMap};
 
-use crate::enemy::Debris;
 use crate::hexling::{Hexling, HEXLING_SPEED};
 use crate::map::Wall;
 use crate::movement::Velocity;
@@ -30,7 +29,6 @@ impl Plugin for CollisionPlugin {
                 collision_detection,
                 handle_player_collisions,
                 handle_hexling_collisions,
-                handle_debris_collisions,
             )
                 .chain(),
         );
@@ -46,19 +44,16 @@ fn collision_detection(mut query: Query<(Entity, &Aabb, &Transform, &mut Collide
             if entity_a != entity_b {
                 // TODO: Why does using the `.center` of the Aabb here result in very odd numbers
                 // for the player's x value? e.g. -3.x * 10^-6 or some such nonsense.
-                match collide_aabb::collide(
+                if let Some(c) = collide_aabb::collide(
                     transform_a.translation,
                     Vec2::new(aabb_a.half_extents.x * 2., aabb_a.half_extents.y * 2.),
                     transform_b.translation,
                     Vec2::new(aabb_b.half_extents.x * 2., aabb_b.half_extents.y * 2.),
                 ) {
-                    Some(c) => {
-                        colliding_entities
-                            .entry(entity_a)
-                            .or_insert_with(Vec::new)
-                            .push((entity_b, c));
-                    }
-                    None => {}
+                    colliding_entities
+                        .entry(entity_a)
+                        .or_insert_with(Vec::new)
+                        .push((entity_b, c));
                 }
             }
         }
@@ -76,14 +71,15 @@ fn collision_detection(mut query: Query<(Entity, &Aabb, &Transform, &mut Collide
 
 // TODO: remove this monstrosity at the earliest opportunity, and replace it with a proper
 // collision system.
+#[allow(clippy::type_complexity)]
 fn handle_player_collisions(
-    mut query: Query<(&Collider, &mut Transform, &mut Velocity), With<Player>>,
-    time: Res<Time>,
-    wall_query: Query<&Wall>,
+    mut query: Query<(&Collider, &mut Velocity), With<Player>>,
+    // Hexlings are as solid as walls, as far as the player is concerned.
+    wall_query: Query<(), Or<(With<Wall>, With<Hexling>)>>,
 ) {
-    for (collider, mut transform, mut velocity) in query.iter_mut() {
+    for (collider, mut velocity) in query.iter_mut() {
         for &collided_entity in collider.colliding_entities.iter() {
-            if wall_query.get(collided_entity.0).is_ok() {
+            if wall_query.contains(collided_entity.0) {
                 match collided_entity.1 {
                     collide_aabb::Collision::Top => {
                         // transform.translation.y += (SPEED / 1.25) * time.delta_seconds();
@@ -114,12 +110,18 @@ fn handle_player_collisions(
 }
 
 // TODO: duplication, expedient for now
+#[allow(clippy::type_complexity)]
 fn handle_hexling_collisions(
+    hexling_query: Query<(), With<Hexling>>,
     mut query: Query<(&Collider, &mut Transform), (With<Hexling>, Without<Player>)>,
     time: Res<Time>,
 ) {
     for (collider, mut transform) in query.iter_mut() {
         for &collided_entity in collider.colliding_entities.iter() {
+            // Hexlings keep their distance from each other by flocking (see `steering`).
+            if hexling_query.contains(collided_entity.0) {
+                continue;
+            }
             match collided_entity.1 {
                 collide_aabb::Collision::Top => {
                     transform.translation.y += HEXLING_SPEED * time.delta_seconds();
@@ -141,21 +143,3 @@ fn handle_hexling_collisions(
         }
     }
 }
-
-fn handle_debris_collisions(
-    debris_query: Query<&Debris>,
-    mut query: Query<(&Collider, &mut Velocity), With<Debris>>,
-) {
-    for (collider, mut velocity) in query.iter_mut() {
-        for &collided_entity in collider.colliding_entities.iter() {
-            // Debris only collides with other debris
-            if debris_query.get(collided_entity.0).is_ok() {
-                velocity.value = Vec3::new(
-                    velocity.value.x + rand::random::<f32>() * 0.5 - 0.25,
-                    velocity.value.y + rand::random::<f32>() * 0.5 - 0.25,
-                    0.,
-                );
-            }
-        }
-    }
-}
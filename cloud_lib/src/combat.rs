@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::{damage::DamageKind, GameState};
+
+// Anything that can be hurt, and eventually killed.
+#[derive(Component, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    // Health recovered per second, up to `max`.
+    pub regen: f32,
+    // Damage is ignored entirely while this is set.
+    pub invulnerable: bool,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            regen: 0.,
+            invulnerable: false,
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
+
+// Anything that deals damage to its primary target.
+#[derive(Component, Debug)]
+pub struct Attacker {
+    // Maximum range expressed as distance to target's centre from self's centre.
+    pub attack_range: f32,
+    // Amount by which cooldown is increased following each attack.
+    pub attack_rate: f32,
+    pub base_damage: f32,
+    // When cooldown reduces to 0, an attack can be made. Starts at 0, reduces by
+    // time.delta_seconds() each tick.
+    pub cooldown: f32,
+    pub damage_kind: DamageKind,
+}
+
+// Anything that picks targets.
+#[derive(Component, Debug)]
+pub struct Targeting {
+    // Any entity within this radius will be added to the target_list.
+    pub aggro_radius: f32,
+    // This list contains all targets. They may not still be within aggro_radius. The list may be
+    // re-ordered, and the first entity on the list will always be the primary target.
+    pub target_list: Vec<Entity>,
+}
+
+impl Targeting {
+    pub fn new(aggro_radius: f32) -> Self {
+        Self {
+            aggro_radius,
+            target_list: Vec::new(),
+        }
+    }
+
+    pub fn primary(&self) -> Option<Entity> {
+        self.target_list.first().copied()
+    }
+}
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, regenerate.run_if(in_state(GameState::Playing)));
+    }
+}
+
+fn regenerate(mut query: Query<&mut Health>, time: Res<Time>) {
+    for mut health in query.iter_mut() {
+        if health.regen > 0. && !health.is_dead() && health.current < health.max {
+            let amount = health.regen * time.delta_seconds();
+            health.heal(amount);
+        }
+    }
+}
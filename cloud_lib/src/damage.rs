@@ -0,0 +1,228 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{combat::Health, status::StatusEffects, GameState};
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum DamageKind {
+    // Blunt force: bites, rams, the business end of a hexling.
+    #[default]
+    Kinetic,
+    Energy,
+    Corrosive,
+    Thermal,
+}
+
+// Multipliers applied to incoming damage of each kind. Below 1 resists, above 1 is a weakness, and
+// kinds not listed take full damage.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Resistances(pub &'static [(DamageKind, f32)]);
+
+impl Resistances {
+    pub fn multiplier(&self, kind: DamageKind) -> f32 {
+        self.0
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map_or(1., |(_, multiplier)| *multiplier)
+    }
+}
+
+// All damage goes through here, so resistances and shields are only dealt with in one place.
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    // Whoever dealt the damage, if anyone did. Damage over time has no source.
+    pub source: Option<Entity>,
+    pub amount: f32,
+    pub kind: DamageKind,
+}
+
+// Sent once damage has gone through resistances and shields, for anything that wants to react to
+// the hit actually landing.
+#[derive(Event)]
+pub struct HitEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub amount: f32,
+    pub kind: DamageKind,
+}
+
+// Something whose hits can be taken for it by nearby interceptors, up to `max_share` of each one.
+// The player, for one.
+#[derive(Component)]
+pub struct Warded {
+    pub max_share: f32,
+}
+
+// Steps in front of hits aimed at anything `Warded` within the radius. The more `share`, the more of
+// the hit it takes compared to other interceptors; a share of 1 will take a hit all by itself.
+#[derive(Component, Debug)]
+pub struct Interceptor {
+    pub radius: f32,
+    pub share: f32,
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<HitEvent>()
+            .add_systems(
+                Update,
+                apply_damage
+                    .before(crate::enemy::splodey)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+pub fn apply_damage(
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_hit: EventWriter<HitEvent>,
+    interceptor_query: Query<(Entity, &Interceptor, &Transform)>,
+    mut query: Query<(
+        &mut Health,
+        Option<&Resistances>,
+        Option<&mut StatusEffects>,
+    )>,
+    ward_query: Query<(&Transform, &Warded)>,
+) {
+    for ev in ev_damage.read() {
+        for (target, amount) in intercept(ev, &interceptor_query, &query, &ward_query) {
+            let Ok((mut health, resistances, effects)) = query.get_mut(target) else {
+                continue;
+            };
+            if health.invulnerable {
+                continue;
+            }
+            let damage = amount * resistances.map_or(1., |r| r.multiplier(ev.kind));
+            let damage = match effects {
+                Some(mut effects) => effects.absorb(damage),
+                None => damage,
+            };
+            if damage <= 0. {
+                continue;
+            }
+            health.current -= damage;
+            ev_hit.send(HitEvent {
+                target,
+                source: ev.source,
+                amount: damage,
+                kind: ev.kind,
+            });
+        }
+    }
+}
+
+// Splits a hit between a warded target and the living interceptors in reach of it. Interceptors take
+// shares in proportion to their `share`, up to the ward's `max_share` of the hit between them.
+fn intercept(
+    ev: &DamageEvent,
+    interceptor_query: &Query<(Entity, &Interceptor, &Transform)>,
+    query: &Query<(
+        &mut Health,
+        Option<&Resistances>,
+        Option<&mut StatusEffects>,
+    )>,
+    ward_query: &Query<(&Transform, &Warded)>,
+) -> HashMap<Entity, f32> {
+    let mut shares = HashMap::from([(ev.target, ev.amount)]);
+    let Ok((ward_transform, ward)) = ward_query.get(ev.target) else {
+        return shares;
+    };
+    // Nothing to take a hit for if it wouldn't land anyway.
+    if query.get(ev.target).is_ok_and(|(h, _, _)| h.invulnerable) {
+        return shares;
+    }
+    let interceptors: Vec<(Entity, f32)> = interceptor_query
+        .iter()
+        .filter(|(entity, _, _)| query.get(*entity).is_ok_and(|(h, _, _)| !h.is_dead()))
+        .filter(|(_, interceptor, transform)| {
+            (transform.translation - ward_transform.translation).length() < interceptor.radius
+        })
+        .map(|(entity, interceptor, _)| (entity, interceptor.share.max(0.)))
+        .collect();
+    let total: f32 = interceptors.iter().map(|(_, share)| share).sum();
+    if total <= 0. {
+        return shares;
+    }
+
+    let intercepted = ev.amount * total.min(ward.max_share);
+    shares.insert(ev.target, ev.amount - intercepted);
+    for (entity, share) in interceptors {
+        shares.insert(entity, intercepted * share / total);
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interceptors_take_hits_for_the_warded() {
+        let mut app = App::new();
+        app.add_event::<DamageEvent>()
+            .add_event::<HitEvent>()
+            .add_systems(Update, apply_damage);
+        let player = app
+            .world
+            .spawn((
+                Health::new(10.),
+                Transform::default(),
+                Warded { max_share: 0.75 },
+            ))
+            .id();
+        let near = app
+            .world
+            .spawn((
+                Health::new(10.),
+                Interceptor {
+                    radius: 50.,
+                    share: 1.,
+                },
+                Transform::from_xyz(20., 0., 0.),
+            ))
+            .id();
+        let far = app
+            .world
+            .spawn((
+                Health::new(10.),
+                Interceptor {
+                    radius: 50.,
+                    share: 1.,
+                },
+                Transform::from_xyz(80., 0., 0.),
+            ))
+            .id();
+
+        app.world.send_event(DamageEvent {
+            target: player,
+            source: None,
+            amount: 4.,
+            kind: DamageKind::Kinetic,
+        });
+        app.update();
+
+        let health = |app: &App, entity| app.world.get::<Health>(entity).unwrap().current;
+        assert_eq!(health(&app, player), 9.);
+        assert_eq!(health(&app, near), 7.);
+        assert_eq!(health(&app, far), 10.);
+
+        // A second interceptor in reach splits the intercepted part by share.
+        app.world
+            .entity_mut(far)
+            .insert(Transform::from_xyz(-20., 0., 0.));
+        app.world.get_mut::<Interceptor>(far).unwrap().share = 0.5;
+        app.world.send_event(DamageEvent {
+            target: player,
+            source: None,
+            amount: 4.,
+            kind: DamageKind::Kinetic,
+        });
+        app.update();
+        assert_eq!(health(&app, player), 8.);
+        assert_eq!(health(&app, near), 5.);
+        assert_eq!(health(&app, far), 9.);
+    }
+}
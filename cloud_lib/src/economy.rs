@@ -0,0 +1,164 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+};
+
+use crate::{
+    collision::Collider, enemy::Debris, hexling::Hexling, movement::Velocity, player::Player,
+    shapes::ShapeCache, sound::SoundSettings, GameState,
+};
+
+// Debris this close to a collider's edge is picked up.
+const COLLECT_RANGE: f32 = 4.;
+// Debris within this distance of the player or a hexling is drawn towards them...
+const MAGNET_RADIUS: f32 = 70.;
+// ...faster the closer it gets.
+const MAGNET_SPEED: f32 = 240.;
+const SPARK_COLOR: Color = Color::rgba(1.6, 1.4, 0.6, 0.8);
+const SPARK_RADIUS: f32 = 8.;
+const SPARK_SECONDS: f32 = 0.25;
+// Enough for a handful of hexlings to get going with.
+const STARTING_MATTER: f32 = 12.;
+
+// Everything the swarm has gathered over the run.
+#[derive(Resource, Debug)]
+pub struct Stockpile {
+    pub matter: f32,
+}
+
+impl Default for Stockpile {
+    fn default() -> Self {
+        Self {
+            matter: STARTING_MATTER,
+        }
+    }
+}
+
+// A glint where a piece of debris was picked up.
+#[derive(Component)]
+struct Spark {
+    timer: f32,
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stockpile>()
+            .add_systems(OnExit(GameState::Over), reset_stockpile)
+            .add_systems(
+                OnEnter(GameState::Over),
+                crate::menu::despawn_thing::<Spark>,
+            )
+            .add_systems(
+                Update,
+                (magnetise_debris, collect_debris, fade_sparks)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn reset_stockpile(mut stockpile: ResMut<Stockpile>) {
+    *stockpile = Stockpile::default();
+}
+
+// Pulls debris worth anything towards whichever collector is nearest.
+#[allow(clippy::type_complexity)]
+fn magnetise_debris(
+    collector_query: Query<&Transform, (Or<(With<Hexling>, With<Player>)>, Without<Debris>)>,
+    mut query: Query<(&Debris, &Transform, &mut Velocity)>,
+) {
+    for (debris, transform, mut velocity) in query.iter_mut() {
+        if debris.matter <= 0. {
+            continue;
+        }
+        let nearest = collector_query
+            .iter()
+            .map(|collector| collector.translation - transform.translation)
+            .filter(|offset| offset.length() < MAGNET_RADIUS)
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+        if let Some(offset) = nearest {
+            let pull = 1. - offset.length() / MAGNET_RADIUS;
+            velocity.value = offset.normalize_or_zero() * MAGNET_SPEED * (0.25 + pull);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn collect_debris(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    collector_query: Query<
+        (&Collider, &Transform),
+        (Or<(With<Hexling>, With<Player>)>, Without<Debris>),
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<(Entity, &Debris, &Transform)>,
+    mut shapes: ResMut<ShapeCache>,
+    sound_settings: Res<SoundSettings>,
+    mut stockpile: ResMut<Stockpile>,
+) {
+    let mut collected = 0;
+    for (entity, debris, transform) in query.iter() {
+        if debris.matter <= 0. {
+            continue;
+        }
+        let touching = collector_query.iter().any(|(collider, collector)| {
+            (collector.translation - transform.translation).length()
+                < collider.radius + COLLECT_RANGE
+        });
+        if !touching {
+            continue;
+        }
+        stockpile.matter += debris.matter;
+        commands.entity(entity).despawn_recursive();
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: shapes.mesh(&mut meshes, 6, SPARK_RADIUS),
+                // Its own material, as each spark fades on its own.
+                material: materials.add(ColorMaterial::from(SPARK_COLOR)),
+                transform: Transform::from_translation(transform.translation),
+                ..default()
+            },
+            Spark {
+                timer: SPARK_SECONDS,
+            },
+        ));
+        collected += 1;
+    }
+
+    // One tick however much was picked up this frame, so a whole burst doesn't deafen.
+    if collected > 0 {
+        commands.spawn((AudioBundle {
+            source: asset_server.load("audio/tap-tap.ogg"),
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Once,
+                volume: Volume::new_relative(sound_settings.effects_volume / 3.),
+                ..default()
+            },
+        },));
+    }
+}
+
+fn fade_sparks(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(Entity, &Handle<ColorMaterial>, &mut Spark, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, handle, mut spark, mut transform) in query.iter_mut() {
+        spark.timer -= time.delta_seconds();
+        if spark.timer <= 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let left = spark.timer / SPARK_SECONDS;
+        transform.scale = Vec3::splat(2. - left);
+        if let Some(material) = materials.get_mut(handle) {
+            material.color.set_a(SPARK_COLOR.a() * left);
+        }
+    }
+}
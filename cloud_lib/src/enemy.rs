@@ -1,3 +1,5 @@
+pub mod behaviour;
+
 use bevy::{
     audio::{PlaybackMode, Volume},
     prelude::*,
@@ -5,23 +7,48 @@ use bevy::{
 };
 
 use crate::collision::Collider;
-use crate::movement::{MovingEntityBundle, Velocity};
+use crate::combat::{Attacker, Health, Targeting};
+use crate::damage::{DamageEvent, DamageKind, Resistances};
+use crate::movement::{Locomotion, LocomotionProfile, MovingEntityBundle, Velocity};
+use crate::navigation::{NavAgent, NavGrid};
+use crate::particles::Particles;
 use crate::player::Player;
+use crate::shapes::ShapeCache;
 use crate::sound::SoundSettings;
+use crate::status::StatusEffects;
 use crate::GameState;
+use behaviour::{Behaviour, EnemyArchetype, EnemyState, Trigger, OCTAGON, SQUARE};
 
 pub const COLOR: Color = Color::rgb(0.9, 0.0, 0.1);
+// Each shard of an enemy is worth this much, once a hexling or the player picks it up.
+const DEBRIS_MATTER: f32 = 0.25;
 pub const RADIUS: f32 = 20.;
-const ORBIT_POINT: Vec3 = Vec3::new(-250., 350., 0.);
-const SPEED: f32 = 50.;
+// Targets further than this multiple of the aggro radius are dropped from the target list.
+const LEASH_FACTOR: f32 = 2.;
+const STARTING_HEALTH: f32 = 3.;
 pub const STARTING_TRANSLATION: Vec3 = Vec3::new(-300., 400., 0.);
 
 #[derive(Component)]
 pub struct Enemy;
 
+// Marks an enemy in the middle of its wind-up tell, so it can be settled back afterwards.
+#[derive(Component)]
+struct Swollen;
+
+// How an enemy goes to pieces: the particle preset, the colour of the shards and how many there
+// are. Enemies without one burst like an octagon.
+#[derive(Component)]
+pub struct DeathBurst {
+    pub preset: &'static str,
+    pub color: Color,
+    pub shards: usize,
+}
+
+// Pieces of something that blew up. How long they last is up to `particles`.
 #[derive(Component)]
 pub struct Debris {
-    pub despawn_timer: f32,
+    // What it's worth to the stockpile. Debris worth nothing can't be picked up.
+    pub matter: f32,
 }
 
 pub struct EnemyPlugin;
@@ -35,221 +62,232 @@ impl Plugin for EnemyPlugin {
                 Update,
                 (
                     maintain_target_list,
-                    passive_motion,
-                    aggro_motion,
+                    update_behaviour,
+                    behaviour_motion,
+                    wind_up_tell,
+                    attack_target,
                     splodey,
-                    despawn_debris,
                 )
                     .chain()
                     .run_if(in_state(GameState::Playing)),
-            )
-            .add_systems(Update, attack_target.run_if(in_state(GameState::Playing)));
+            );
     }
 }
 
-#[derive(Component)]
-pub struct CombatStats {
-    // Any entity within this radius will be added to the target_list.
-    pub aggro_radius: f32,
-    // Maximum range expressed as distance to target's centre from self's centre.
-    pub attack_range: f32,
-    // Amount by which cooldown is increased following each attack.
-    pub attack_rate: f32,
-    pub base_damage: f32,
-    // When cooldown reduces to 0, an attack can be made. Starts at 0, reduces by
-    // time.delta_seconds() each tick.
-    pub cooldown: f32,
-    pub debris_despawn_timer: f32,
-    pub health: f32,
-    // This list contains all targets. They may not still be within aggro_radius. The list may be
-    // re-ordered, and the first entity on the list will always be the primary target.
-    pub target_list: Vec<Entity>,
-}
-
 pub fn spawn_enemy(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shapes: ResMut<ShapeCache>,
 ) {
+    for (archetype, translation) in [
+        (&OCTAGON, STARTING_TRANSLATION),
+        (&SQUARE, Vec3::new(300., 400., 0.)),
+        (&OCTAGON, Vec3::new(-300., -400., 0.)),
+        (&SQUARE, Vec3::new(300., -400., 0.)),
+    ] {
+        spawn_archetype(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut shapes,
+            archetype,
+            translation,
+        );
+    }
+}
+
+pub fn spawn_octagon(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    translation: Vec3,
+) -> Entity {
+    spawn_archetype(commands, meshes, materials, shapes, &OCTAGON, translation)
+}
+
+pub fn spawn_archetype(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    archetype: &'static EnemyArchetype,
+    translation: Vec3,
+) -> Entity {
     let shape = MaterialMesh2dBundle {
-        mesh: meshes
-            .add(shape::RegularPolygon::new(RADIUS, 8).into())
-            .into(),
-        material: materials.add(ColorMaterial::from(COLOR)),
-        transform: Transform::from_translation(STARTING_TRANSLATION),
+        mesh: shapes.mesh(meshes, archetype.sides, RADIUS),
+        material: shapes.material(materials, COLOR),
+        transform: Transform::from_translation(translation),
         ..default()
     };
 
     commands
         .spawn((
             AnimationPlayer::default(),
-            CombatStats {
-                aggro_radius: 200.,
+            Attacker {
                 attack_range: 100.,
                 attack_rate: 10.,
                 base_damage: 1.,
                 cooldown: 0.,
-                debris_despawn_timer: 10.,
-                health: 3.,
-                target_list: Vec::new(),
+                damage_kind: DamageKind::Kinetic,
             },
+            Behaviour::new(archetype, translation),
+            Health::new(STARTING_HEALTH),
+            Locomotion::new(LocomotionProfile::Enemy),
             MovingEntityBundle {
                 collider: Collider::new(RADIUS),
                 shape,
                 velocity: Velocity::new(Vec3::ZERO),
             },
-            Name::new("enemy"),
-        ))
-        .insert(Enemy);
-
-    commands
-        .spawn((
-            AnimationPlayer::default(),
-            CombatStats {
-                aggro_radius: 200.,
-                attack_range: 100.,
-                attack_rate: 10.,
-                base_damage: 1.,
-                cooldown: 0.,
-                debris_despawn_timer: 10.,
-                health: 3.,
-                target_list: Vec::new(),
-            },
-            MovingEntityBundle {
-                collider: Collider::new(RADIUS),
-                shape: MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::RegularPolygon::new(RADIUS, 8).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(COLOR)),
-                    transform: Transform::from_translation(Vec3::new(300., 400., 0.)),
-                    ..default()
-                },
-                velocity: Velocity::new(Vec3::ZERO),
-            },
-            Name::new("enemy"),
+            Name::new(archetype.name),
+            NavAgent::default(),
+            Resistances(archetype.resistances),
+            Targeting::new(200.),
         ))
-        .insert(Enemy);
+        .insert(Enemy)
+        .id()
+}
 
-    commands
-        .spawn((
-            AnimationPlayer::default(),
-            CombatStats {
-                aggro_radius: 200.,
-                attack_range: 100.,
-                attack_rate: 10.,
-                base_damage: 1.,
-                cooldown: 0.,
-                debris_despawn_timer: 10.,
-                health: 3.,
-                target_list: Vec::new(),
-            },
-            MovingEntityBundle {
-                collider: Collider::new(RADIUS),
-                shape: MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::RegularPolygon::new(RADIUS, 8).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(COLOR)),
-                    transform: Transform::from_translation(Vec3::new(-300., -400., 0.)),
-                    ..default()
-                },
-                velocity: Velocity::new(Vec3::ZERO),
-            },
-            Name::new("enemy"),
-        ))
-        .insert(Enemy);
+fn update_behaviour(
+    mut enemy_query: Query<
+        (&Attacker, &mut Behaviour, &Health, &Targeting, &Transform),
+        With<Enemy>,
+    >,
+    friendly_query: Query<&Transform, (With<Health>, Without<Enemy>)>,
+    time: Res<Time>,
+) {
+    for (attacker, mut behaviour, health, targeting, transform) in enemy_query.iter_mut() {
+        behaviour.timer += time.delta_seconds();
 
-    commands
-        .spawn((
-            AnimationPlayer::default(),
-            CombatStats {
-                aggro_radius: 200.,
-                attack_range: 100.,
-                attack_rate: 10.,
-                base_damage: 1.,
-                cooldown: 0.,
-                debris_despawn_timer: 10.,
-                health: 3.,
-                target_list: Vec::new(),
-            },
-            MovingEntityBundle {
-                collider: Collider::new(RADIUS),
-                shape: MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::RegularPolygon::new(RADIUS, 8).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(COLOR)),
-                    transform: Transform::from_translation(Vec3::new(300., -400., 0.)),
-                    ..default()
-                },
-                velocity: Velocity::new(Vec3::ZERO),
-            },
-            Name::new("enemy"),
-        ))
-        .insert(Enemy);
+        let target_distance = targeting
+            .primary()
+            .and_then(|target| friendly_query.get(target).ok())
+            .map(|target| (target.translation - transform.translation).length());
+        let next = behaviour.next_state(|trigger| match trigger {
+            Trigger::TargetAcquired => target_distance.is_some(),
+            Trigger::TargetLost => target_distance.is_none(),
+            Trigger::ReadyToAttack => {
+                attacker.cooldown <= 0.
+                    && target_distance.is_some_and(|d| d < attacker.attack_range)
+            }
+            Trigger::TargetOutOfRange => target_distance.is_none_or(|d| d >= attacker.attack_range),
+            Trigger::LowHealth => health.fraction() < behaviour.archetype.flee_health,
+            Trigger::TimerElapsed => false,
+        });
+        if let Some(state) = next {
+            behaviour.enter(state);
+        }
+    }
 }
 
-fn passive_motion(
-    mut query: Query<(&CombatStats, &mut Transform, &mut Velocity), With<Enemy>>,
+fn behaviour_motion(
+    mut enemy_query: Query<
+        (
+            &Behaviour,
+            &mut Locomotion,
+            &mut NavAgent,
+            &Targeting,
+            &mut Transform,
+        ),
+        With<Enemy>,
+    >,
+    friendly_query: Query<&Transform, (With<Health>, Without<Enemy>)>,
+    nav_grid: Res<NavGrid>,
     time: Res<Time>,
 ) {
-    for (stats, mut transform, mut velocity) in query.iter_mut() {
+    for (behaviour, mut locomotion, mut agent, targeting, mut transform) in enemy_query.iter_mut() {
         transform.rotate_z(3. * time.delta_seconds());
-        if !stats.target_list.is_empty() {
-            // Has at least one target: passive motion doesn't apply
-            continue;
-        }
 
-        // Orbit fixed point
-        let direction = (ORBIT_POINT - transform.translation).normalize();
-        let perpendicular = Vec3::new(-direction.y, direction.x, 0.);
-        velocity.value = perpendicular * SPEED;
+        let archetype = behaviour.archetype;
+        let target = targeting
+            .primary()
+            .and_then(|target| friendly_query.get(target).ok())
+            .map(|target| target.translation);
+
+        locomotion.intent = match (behaviour.state, target) {
+            (EnemyState::Patrol, _) => {
+                // Orbit home, drifting back toward the patrol radius if we've wandered.
+                let offset = behaviour.home - transform.translation;
+                let direction = offset.normalize_or_zero();
+                let perpendicular = Vec3::new(-direction.y, direction.x, 0.);
+                let drift = direction * (offset.length() - archetype.patrol_radius)
+                    / archetype.patrol_radius;
+                (perpendicular + drift).normalize_or_zero() * archetype.patrol_speed
+            }
+            (EnemyState::Chase, Some(target)) => {
+                agent.direction(
+                    &nav_grid,
+                    transform.translation,
+                    target,
+                    time.delta_seconds(),
+                ) * archetype.chase_speed
+            }
+            (EnemyState::Flee, Some(target)) => {
+                let away = transform.translation * 2. - target;
+                agent.direction(&nav_grid, transform.translation, away, time.delta_seconds())
+                    * archetype.flee_speed
+            }
+            // Idle, alert, winding up and recovering enemies hold their ground.
+            _ => Vec3::ZERO,
+        };
     }
 }
 
-fn aggro_motion(
-    mut enemy_query: Query<(&CombatStats, &mut Transform, &mut Velocity), With<Enemy>>,
-    friendly_query: Query<&Transform, (With<CombatStats>, Without<Enemy>)>,
+// The tell: an enemy winding up swells and pulses, settling back once the blow lands. Enemies that
+// aren't winding up are left alone, so other things can scale them.
+fn wind_up_tell(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Behaviour, Has<Swollen>, &mut Transform), With<Enemy>>,
 ) {
-    for (stats, transform, mut velocity) in enemy_query.iter_mut() {
-        if stats.target_list.is_empty() {
-            // No targets. Leash to spawn point.
-            continue;
+    for (entity, behaviour, swollen, mut transform) in query.iter_mut() {
+        if behaviour.state == EnemyState::WindUp {
+            let progress = (behaviour.timer / behaviour.duration()).min(1.);
+            let pulse = (progress * std::f32::consts::PI * 3.).sin().abs();
+            let scale = 1. + behaviour.archetype.wind_up_scale * progress * pulse;
+            transform.scale = Vec3::new(scale, scale, 1.);
+            if !swollen {
+                commands.entity(entity).insert(Swollen);
+            }
+        } else if swollen {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Swollen>();
         }
-
-        let Ok(target) = friendly_query.get(stats.target_list.first().unwrap().to_owned()) else {
-            return;
-        };
-
-        let direction = target.translation - transform.translation;
-        velocity.value = direction.normalize() * SPEED;
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn maintain_target_list(
-    mut enemy_query: Query<(&mut CombatStats, &Transform), With<Enemy>>,
-    friendly_query: Query<(Entity, &Transform), (With<CombatStats>, Without<Enemy>)>,
+    mut enemy_query: Query<(&mut Targeting, &Transform), With<Enemy>>,
+    friendly_query: Query<(Entity, &Transform), (With<Health>, Without<Enemy>)>,
     player_query: Query<Entity, With<Player>>,
 ) {
     let Ok(player_entity) = player_query.get_single() else {
         return;
     };
 
-    for (mut stats, transform) in enemy_query.iter_mut() {
+    for (mut targeting, transform) in enemy_query.iter_mut() {
         for (friendly_entity, friendly_transform) in friendly_query.iter() {
             let direction = transform.translation - friendly_transform.translation;
-            if direction.length() < stats.aggro_radius
-                && !stats.target_list.contains(&friendly_entity)
+            if direction.length() < targeting.aggro_radius
+                && !targeting.target_list.contains(&friendly_entity)
             {
-                stats.target_list.push(friendly_entity);
+                targeting.target_list.push(friendly_entity);
             }
         }
 
+        // Forget targets that have died or given us the slip.
+        let leash = targeting.aggro_radius * LEASH_FACTOR;
+        targeting.target_list.retain(|target| {
+            friendly_query
+                .get(*target)
+                .is_ok_and(|(_, t)| (t.translation - transform.translation).length() < leash)
+        });
+
         // Reorder target list for priority:
         //   - kill player first. Player must die.
         //   - kill closest hexling only if player is not on the target list
-        stats.target_list.sort_by(|a, b| {
+        targeting.target_list.sort_by(|a, b| {
             if a == &player_entity {
                 return std::cmp::Ordering::Less;
             }
@@ -274,25 +312,44 @@ fn maintain_target_list(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn attack_target(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
-    mut enemy_query: Query<(&mut CombatStats, &Transform), With<Enemy>>,
-    mut friendly_query: Query<(&mut CombatStats, &Transform), Without<Enemy>>,
+    mut enemy_query: Query<
+        (
+            Entity,
+            &mut Attacker,
+            &Behaviour,
+            Option<&StatusEffects>,
+            &Targeting,
+            &Transform,
+        ),
+        With<Enemy>,
+    >,
+    mut ev_damage: EventWriter<DamageEvent>,
+    friendly_query: Query<(&Health, &Transform), Without<Enemy>>,
     sound_settings: Res<SoundSettings>,
     time: Res<Time>,
 ) {
-    for (mut stats, transform) in enemy_query.iter_mut() {
-        if stats.target_list.is_empty() {
+    for (entity, mut attacker, behaviour, effects, targeting, transform) in enemy_query.iter_mut() {
+        // Stunned enemies don't even recover from their last attack.
+        if effects.is_some_and(|e| e.is_stunned()) {
             continue;
         }
-        let Ok((mut target_stats, target_transform)) =
-            friendly_query.get_mut(stats.target_list.first().unwrap().to_owned())
+        let Some(target) = targeting
+            .primary()
+            .filter(|_| behaviour.state == EnemyState::Attack)
         else {
-            return;
+            attacker.cooldown -= time.delta_seconds();
+            continue;
+        };
+        let Ok((target_health, target_transform)) = friendly_query.get(target) else {
+            continue;
         };
+        // The wind-up gave the target time to get clear.
         let distance = (transform.translation - target_transform.translation).length();
-        if stats.cooldown <= 0. && target_stats.health > 0. && distance < stats.attack_range {
+        if attacker.cooldown <= 0. && !target_health.is_dead() && distance < attacker.attack_range {
             commands.spawn(AudioBundle {
                 source: asset_server.load("audio/enemy_basic_attack.ogg"),
                 settings: PlaybackSettings {
@@ -302,42 +359,45 @@ fn attack_target(
                 },
             });
 
-            target_stats.health -= stats.base_damage;
-            stats.cooldown = stats.attack_rate * time.delta_seconds();
-        } else {
-            stats.cooldown -= time.delta_seconds();
+            ev_damage.send(DamageEvent {
+                target,
+                source: Some(entity),
+                amount: attacker.base_damage,
+                kind: attacker.damage_kind,
+            });
+            attacker.cooldown = attacker.attack_rate * time.delta_seconds();
         }
     }
 }
 
-fn splodey(
+#[allow(clippy::too_many_arguments)]
+pub fn splodey(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    query: Query<(Entity, &CombatStats, &Transform), With<Enemy>>,
+    particles: Res<Particles>,
+    query: Query<(Entity, &Health, &Transform, Option<&DeathBurst>), With<Enemy>>,
+    mut shapes: ResMut<ShapeCache>,
     sound_settings: Res<SoundSettings>,
 ) {
-    for (entity, stats, transform) in query.iter() {
-        if stats.health <= 0. {
-            for _ in 0..20 {
-                let shape = MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::RegularPolygon::new(6., 3).into()).into(),
-                    material: materials.add(ColorMaterial::from(COLOR)),
-                    transform: Transform::from_translation(transform.translation).with_rotation(
-                        Quat::from_rotation_z(rand::random::<f32>() * 2. * std::f32::consts::PI),
-                    ),
-                    ..default()
-                };
-
-                commands
-                    .spawn(MovingEntityBundle {
-                        collider: Collider::new(6.),
-                        shape,
-                        velocity: Velocity::new(Vec3::ZERO),
-                    })
-                    .insert(Debris { despawn_timer: 10. });
-            }
+    for (entity, health, transform, burst) in query.iter() {
+        if health.is_dead() {
+            let (preset, color, shards) = burst
+                .map(|burst| (burst.preset, burst.color, burst.shards))
+                .unwrap_or(("enemy_death", COLOR, 20));
+            spawn_debris(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut shapes,
+                &particles,
+                preset,
+                transform.translation,
+                color,
+                shards,
+                DEBRIS_MATTER,
+            );
 
             commands.entity(entity).despawn_recursive();
 
@@ -366,16 +426,31 @@ fn splodey(
     }
 }
 
-fn despawn_debris(
-    mut commands: Commands,
-    mut query: Query<(Entity, &mut Debris)>,
-    time: Res<Time>,
+// A burst of `count` shards of `color`, each worth `matter`, left behind by whatever just blew up.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_debris(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    particles: &Particles,
+    preset: &str,
+    translation: Vec3,
+    color: Color,
+    count: usize,
+    matter: f32,
 ) {
-    for (entity, mut debris) in query.iter_mut() {
-        debris.despawn_timer -= time.delta_seconds();
-        if debris.despawn_timer <= 0. {
-            commands.entity(entity).despawn_recursive();
-        }
+    for entity in particles.burst(
+        commands,
+        meshes,
+        materials,
+        shapes,
+        preset,
+        translation,
+        color,
+        count,
+    ) {
+        commands.entity(entity).insert(Debris { matter });
     }
 }
 
@@ -384,3 +459,45 @@ fn despawn_enemy(mut commands: Commands, query: Query<Entity, With<Enemy>>) {
         commands.entity(entity).despawn_recursive();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tell_only_touches_scale_while_winding_up() {
+        let mut app = App::new();
+        app.add_systems(Update, wind_up_tell);
+        let mut behaviour = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        behaviour.enter(EnemyState::Chase);
+        let enemy = app
+            .world
+            .spawn((
+                behaviour,
+                Enemy,
+                Transform::from_scale(Vec3::new(2., 2., 1.)),
+            ))
+            .id();
+        let scale = |app: &App| app.world.get::<Transform>(enemy).unwrap().scale;
+
+        app.update();
+        assert_eq!(scale(&app), Vec3::new(2., 2., 1.));
+
+        let mut behaviour = app.world.get_mut::<Behaviour>(enemy).unwrap();
+        behaviour.enter(EnemyState::WindUp);
+        behaviour.timer = OCTAGON.wind_up_seconds / 2.;
+        app.update();
+        assert!(scale(&app).x > 1.);
+
+        // Settles back once, then leaves it be.
+        app.world
+            .get_mut::<Behaviour>(enemy)
+            .unwrap()
+            .enter(EnemyState::Attack);
+        app.update();
+        assert_eq!(scale(&app), Vec3::ONE);
+        app.world.get_mut::<Transform>(enemy).unwrap().scale = Vec3::splat(0.5);
+        app.update();
+        assert_eq!(scale(&app), Vec3::splat(0.5));
+    }
+}
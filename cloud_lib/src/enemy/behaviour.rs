@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+
+use crate::damage::DamageKind;
+
+use self::EnemyState::*;
+use self::Trigger::*;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum EnemyState {
+    #[default]
+    Idle,
+    Patrol,
+    Alert,
+    Chase,
+    WindUp,
+    Attack,
+    Recover,
+    Flee,
+}
+
+// Everything a transition can be triggered by. Evaluated fresh each tick.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Trigger {
+    // The current state has run for its configured duration.
+    TimerElapsed,
+    TargetAcquired,
+    TargetLost,
+    // Target is within attack range and the attack cooldown has run out.
+    ReadyToAttack,
+    TargetOutOfRange,
+    LowHealth,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    pub from: EnemyState,
+    pub to: EnemyState,
+    pub when: Trigger,
+}
+
+const fn transition(from: EnemyState, to: EnemyState, when: Trigger) -> Transition {
+    Transition { from, to, when }
+}
+
+// Per-archetype configuration. Transitions are checked in order and the first match wins, so more
+// urgent transitions (fleeing, say) should be listed before less urgent ones.
+#[derive(Debug)]
+pub struct EnemyArchetype {
+    pub name: &'static str,
+    pub alert_seconds: f32,
+    pub chase_speed: f32,
+    pub flee_speed: f32,
+    // Fraction of max health below which `LowHealth` holds.
+    pub flee_health: f32,
+    pub flee_seconds: f32,
+    pub idle_seconds: f32,
+    pub patrol_radius: f32,
+    pub patrol_speed: f32,
+    pub recover_seconds: f32,
+    // Incoming damage multipliers; see `damage::Resistances`.
+    pub resistances: &'static [(DamageKind, f32)],
+    // Drawn as a regular polygon with this many sides.
+    pub sides: usize,
+    pub transitions: &'static [Transition],
+    pub wind_up_seconds: f32,
+    // How much the enemy swells at the peak of its wind-up pulse.
+    pub wind_up_scale: f32,
+}
+
+pub const OCTAGON: EnemyArchetype = EnemyArchetype {
+    name: "octagon",
+    alert_seconds: 0.4,
+    chase_speed: 50.,
+    flee_speed: 70.,
+    flee_health: 0.34,
+    flee_seconds: 2.,
+    idle_seconds: 1.,
+    patrol_radius: 70.,
+    patrol_speed: 50.,
+    recover_seconds: 0.8,
+    // Armour plating shrugs off blows but conducts energy nicely.
+    resistances: &[(DamageKind::Kinetic, 0.5), (DamageKind::Energy, 1.5)],
+    sides: 8,
+    transitions: &[
+        transition(Idle, Alert, TargetAcquired),
+        transition(Idle, Patrol, TimerElapsed),
+        transition(Patrol, Alert, TargetAcquired),
+        transition(Alert, Patrol, TargetLost),
+        transition(Alert, Chase, TimerElapsed),
+        transition(Chase, Patrol, TargetLost),
+        // Cornered, a wounded octagon will still bite before it runs.
+        transition(Chase, WindUp, ReadyToAttack),
+        transition(Chase, Flee, LowHealth),
+        transition(WindUp, Attack, TimerElapsed),
+        transition(Attack, Recover, TimerElapsed),
+        transition(Recover, Chase, TimerElapsed),
+        transition(Flee, Patrol, TargetLost),
+        transition(Flee, Chase, TimerElapsed),
+    ],
+    wind_up_seconds: 0.6,
+    wind_up_scale: 0.35,
+};
+
+// Quick and stubborn: never runs, but gives up on a blow if its target slips out of reach.
+pub const SQUARE: EnemyArchetype = EnemyArchetype {
+    name: "square",
+    alert_seconds: 0.2,
+    chase_speed: 75.,
+    flee_speed: 0.,
+    flee_health: 0.,
+    flee_seconds: 0.,
+    idle_seconds: 0.5,
+    patrol_radius: 120.,
+    patrol_speed: 65.,
+    recover_seconds: 1.2,
+    // Soft and squashy, but nothing much eats through it.
+    resistances: &[(DamageKind::Kinetic, 1.25), (DamageKind::Corrosive, 0.5)],
+    sides: 4,
+    transitions: &[
+        transition(Idle, Alert, TargetAcquired),
+        transition(Idle, Patrol, TimerElapsed),
+        transition(Patrol, Alert, TargetAcquired),
+        transition(Alert, Patrol, TargetLost),
+        transition(Alert, Chase, TimerElapsed),
+        transition(Chase, Patrol, TargetLost),
+        transition(Chase, WindUp, ReadyToAttack),
+        transition(WindUp, Chase, TargetOutOfRange),
+        transition(WindUp, Attack, TimerElapsed),
+        transition(Attack, Recover, TimerElapsed),
+        transition(Recover, Patrol, TargetLost),
+        transition(Recover, Chase, TimerElapsed),
+    ],
+    wind_up_seconds: 0.35,
+    wind_up_scale: 0.5,
+};
+
+#[derive(Component)]
+pub struct Behaviour {
+    pub archetype: &'static EnemyArchetype,
+    // Patrols circle this point.
+    pub home: Vec3,
+    pub state: EnemyState,
+    // Seconds spent in the current state.
+    pub timer: f32,
+}
+
+impl Behaviour {
+    pub fn new(archetype: &'static EnemyArchetype, home: Vec3) -> Self {
+        Self {
+            archetype,
+            home,
+            state: EnemyState::Idle,
+            timer: 0.,
+        }
+    }
+
+    // How long the current state lasts before `TimerElapsed` holds. Attack is instantaneous.
+    pub fn duration(&self) -> f32 {
+        match self.state {
+            Idle => self.archetype.idle_seconds,
+            Alert => self.archetype.alert_seconds,
+            WindUp => self.archetype.wind_up_seconds,
+            Attack => 0.,
+            Recover => self.archetype.recover_seconds,
+            Flee => self.archetype.flee_seconds,
+            Patrol | Chase => f32::INFINITY,
+        }
+    }
+
+    // Picks the first configured transition out of the current state whose trigger holds.
+    pub fn next_state(&self, holds: impl Fn(Trigger) -> bool) -> Option<EnemyState> {
+        self.archetype
+            .transitions
+            .iter()
+            .filter(|t| t.from == self.state)
+            .find(|t| match t.when {
+                TimerElapsed => self.timer >= self.duration(),
+                trigger => holds(trigger),
+            })
+            .map(|t| t.to)
+    }
+
+    pub fn enter(&mut self, state: EnemyState) {
+        self.state = state;
+        self.timer = 0.;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Steps `behaviour` once, with `holding` as the only triggers (besides its timer) that hold.
+    fn step(behaviour: &mut Behaviour, holding: &[Trigger]) -> EnemyState {
+        if let Some(state) = behaviour.next_state(|trigger| holding.contains(&trigger)) {
+            behaviour.enter(state);
+        }
+        behaviour.state
+    }
+
+    #[test]
+    fn idle_patrols_then_notices() {
+        let mut behaviour = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        assert_eq!(step(&mut behaviour, &[]), Idle);
+        behaviour.timer = OCTAGON.idle_seconds;
+        assert_eq!(step(&mut behaviour, &[]), Patrol);
+        // Patrols last until something turns up.
+        behaviour.timer = 1000.;
+        assert_eq!(step(&mut behaviour, &[TargetLost]), Patrol);
+        assert_eq!(step(&mut behaviour, &[TargetAcquired]), Alert);
+
+        // Seeing a target beats getting bored.
+        let mut behaviour = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        behaviour.timer = OCTAGON.idle_seconds;
+        assert_eq!(step(&mut behaviour, &[TargetAcquired]), Alert);
+    }
+
+    #[test]
+    fn attacks_wind_up_land_and_recover() {
+        let mut behaviour = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        behaviour.enter(Chase);
+        assert_eq!(step(&mut behaviour, &[TargetAcquired]), Chase);
+        assert_eq!(step(&mut behaviour, &[ReadyToAttack]), WindUp);
+        assert_eq!(step(&mut behaviour, &[]), WindUp);
+        behaviour.timer = OCTAGON.wind_up_seconds;
+        assert_eq!(step(&mut behaviour, &[]), Attack);
+        // The blow itself takes no time at all.
+        assert_eq!(step(&mut behaviour, &[]), Recover);
+        behaviour.timer = OCTAGON.recover_seconds;
+        assert_eq!(step(&mut behaviour, &[]), Chase);
+    }
+
+    #[test]
+    fn wounded_octagons_bite_before_they_run() {
+        let mut behaviour = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        behaviour.enter(Chase);
+        assert_eq!(step(&mut behaviour, &[LowHealth, ReadyToAttack]), WindUp);
+
+        behaviour.enter(Chase);
+        assert_eq!(step(&mut behaviour, &[LowHealth]), Flee);
+        behaviour.timer = OCTAGON.flee_seconds;
+        assert_eq!(step(&mut behaviour, &[LowHealth]), Chase);
+    }
+
+    #[test]
+    fn archetypes_follow_their_own_transitions() {
+        // Squares never run...
+        let mut square = Behaviour::new(&SQUARE, Vec3::ZERO);
+        square.enter(Chase);
+        assert_eq!(step(&mut square, &[LowHealth]), Chase);
+
+        // ...but do give up on a blow that can't land, where an octagon commits to it.
+        let mut octagon = Behaviour::new(&OCTAGON, Vec3::ZERO);
+        for behaviour in [&mut square, &mut octagon] {
+            behaviour.enter(WindUp);
+        }
+        assert_eq!(step(&mut square, &[TargetOutOfRange]), Chase);
+        assert_eq!(step(&mut octagon, &[TargetOutOfRange]), WindUp);
+
+        // And wind up quicker, too.
+        for behaviour in [&mut square, &mut octagon] {
+            behaviour.enter(WindUp);
+            behaviour.timer = SQUARE.wind_up_seconds;
+        }
+        assert_eq!(step(&mut square, &[]), Attack);
+        assert_eq!(step(&mut octagon, &[]), WindUp);
+    }
+}
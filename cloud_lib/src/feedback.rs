@@ -0,0 +1,316 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    damage::{DamageKind, HitEvent},
+    movement::Velocity,
+    shapes::ShapeCache,
+    GameState,
+};
+
+const FLASH_COLOR: Color = Color::rgb(4., 4., 4.);
+const FLASH_SECONDS: f32 = 0.08;
+// Impulse per point of damage, and the most any single hit can shove.
+const KNOCKBACK: f32 = 150.;
+const MAX_KNOCKBACK: f32 = 300.;
+// Small hits (contact and damage over time, mostly) are totted up until they're worth showing.
+const NUMBER_MIN_DAMAGE: f32 = 0.5;
+const NUMBER_RISE: f32 = 30.;
+const NUMBER_SECONDS: f32 = 0.8;
+const NUMBER_SIZE: f32 = 14.;
+
+#[derive(Resource)]
+pub struct FeedbackSettings {
+    pub damage_numbers: bool,
+    pub flash: bool,
+    pub knockback: bool,
+}
+
+// A struck entity, shown in `FLASH_COLOR` until the timer runs out. Anything recolouring a flashing
+// entity should change `restore` instead, or the flash will undo it.
+#[derive(Component)]
+pub struct Flash {
+    pub restore: Handle<ColorMaterial>,
+    timer: f32,
+}
+
+#[derive(Component)]
+pub struct DamageNumber {
+    timer: f32,
+}
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FeedbackSettings {
+            damage_numbers: true,
+            flash: true,
+            knockback: true,
+        })
+        .add_systems(
+            OnEnter(GameState::Over),
+            crate::menu::despawn_thing::<DamageNumber>,
+        )
+        .add_systems(
+            Update,
+            (
+                (flash, damage_numbers, knockback).after(crate::damage::apply_damage),
+                fade_flash,
+                float_numbers,
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn flash(
+    mut commands: Commands,
+    mut ev_hit: EventReader<HitEvent>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&mut Handle<ColorMaterial>, Option<&mut Flash>)>,
+    settings: Res<FeedbackSettings>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    if !settings.flash {
+        ev_hit.clear();
+        return;
+    }
+
+    // Flash components inserted this frame aren't visible to the query yet; without this, a second
+    // hit would remember the flash material as the one to restore.
+    let mut flashed = HashSet::new();
+    for ev in ev_hit.read() {
+        let Ok((mut handle, existing)) = query.get_mut(ev.target) else {
+            continue;
+        };
+        if let Some(mut existing) = existing {
+            existing.timer = FLASH_SECONDS;
+            continue;
+        }
+        if !flashed.insert(ev.target) {
+            continue;
+        }
+        // The hit may well have been fatal, in which case there's nothing left to flash.
+        commands.entity(ev.target).try_insert(Flash {
+            restore: handle.clone(),
+            timer: FLASH_SECONDS,
+        });
+        *handle = shapes.material(&mut materials, FLASH_COLOR);
+    }
+}
+
+fn fade_flash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Flash, &mut Handle<ColorMaterial>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut handle) in query.iter_mut() {
+        flash.timer -= time.delta_seconds();
+        if flash.timer > 0. {
+            continue;
+        }
+        *handle = flash.restore.clone();
+        commands.entity(entity).remove::<Flash>();
+    }
+}
+
+fn damage_numbers(
+    mut commands: Commands,
+    mut ev_hit: EventReader<HitEvent>,
+    // Damage dealt to each entity that hasn't been shown yet.
+    mut pending: Local<HashMap<Entity, f32>>,
+    query: Query<&Transform>,
+    settings: Res<FeedbackSettings>,
+) {
+    if !settings.damage_numbers {
+        ev_hit.clear();
+        return;
+    }
+    // Forget whatever died before its damage added up to anything.
+    pending.retain(|entity, _| query.contains(*entity));
+
+    for ev in ev_hit.read() {
+        let amount = pending.entry(ev.target).or_default();
+        *amount += ev.amount;
+        if *amount < NUMBER_MIN_DAMAGE {
+            continue;
+        }
+        let shown = (*amount * 10.).round() / 10.;
+        pending.remove(&ev.target);
+
+        let Ok(transform) = query.get(ev.target) else {
+            continue;
+        };
+        commands.spawn((
+            DamageNumber {
+                timer: NUMBER_SECONDS,
+            },
+            Name::new("damage number"),
+            Text2dBundle {
+                text: Text::from_section(
+                    shown.to_string(),
+                    TextStyle {
+                        color: number_color(ev.kind),
+                        font_size: NUMBER_SIZE,
+                        ..default()
+                    },
+                ),
+                // Float above whatever was hit.
+                transform: Transform::from_translation(
+                    transform.translation.truncate().extend(10.),
+                ),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn float_numbers(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut DamageNumber, &mut Text, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut text, mut transform) in query.iter_mut() {
+        number.timer -= time.delta_seconds();
+        if number.timer <= 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation.y += NUMBER_RISE * time.delta_seconds();
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(number.timer / NUMBER_SECONDS);
+        }
+    }
+}
+
+// Shoves whatever was hit directly away from whoever hit it.
+fn knockback(
+    mut ev_hit: EventReader<HitEvent>,
+    mut query: Query<(&Transform, &mut Velocity)>,
+    settings: Res<FeedbackSettings>,
+    sources: Query<&Transform>,
+) {
+    if !settings.knockback {
+        ev_hit.clear();
+        return;
+    }
+
+    for ev in ev_hit.read() {
+        let Some(source) = ev.source.and_then(|source| sources.get(source).ok()) else {
+            continue;
+        };
+        let Ok((transform, mut velocity)) = query.get_mut(ev.target) else {
+            continue;
+        };
+        let away = (transform.translation - source.translation)
+            .truncate()
+            .normalize_or_zero()
+            .extend(0.);
+        velocity.impulse =
+            (velocity.impulse + away * ev.amount * KNOCKBACK).clamp_length_max(MAX_KNOCKBACK);
+    }
+}
+
+fn number_color(kind: DamageKind) -> Color {
+    match kind {
+        DamageKind::Kinetic => Color::WHITE,
+        DamageKind::Energy => Color::rgb(0.4, 0.8, 1.),
+        DamageKind::Corrosive => Color::rgb(0.7, 1., 0.2),
+        DamageKind::Thermal => Color::rgb(1., 0.5, 0.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<ShapeCache>()
+            .init_resource::<Time>()
+            .insert_resource(FeedbackSettings {
+                damage_numbers: true,
+                flash: true,
+                knockback: true,
+            })
+            .add_event::<HitEvent>();
+        app
+    }
+
+    fn hit(app: &mut App, target: Entity, amount: f32) {
+        app.world.send_event(HitEvent {
+            target,
+            source: None,
+            amount,
+            kind: DamageKind::Kinetic,
+        });
+    }
+
+    #[test]
+    fn flash_wears_off_to_the_original_material() {
+        let mut app = app();
+        app.add_systems(Update, (flash, apply_deferred, fade_flash).chain());
+        let original = app
+            .world
+            .resource_mut::<Assets<ColorMaterial>>()
+            .add(ColorMaterial::from(Color::GREEN));
+        let target = app.world.spawn(original.clone()).id();
+        // Twice in the same frame, so the second hit sees the flash material.
+        hit(&mut app, target, 1.);
+        hit(&mut app, target, 1.);
+        app.update();
+        let flashed = app.world.get::<Handle<ColorMaterial>>(target).unwrap();
+        let color = app
+            .world
+            .resource::<Assets<ColorMaterial>>()
+            .get(flashed)
+            .unwrap()
+            .color;
+        assert_eq!(color, FLASH_COLOR);
+
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(FLASH_SECONDS * 2.));
+        app.update();
+        assert_eq!(
+            app.world.get::<Handle<ColorMaterial>>(target),
+            Some(&original)
+        );
+        assert!(app.world.get::<Flash>(target).is_none());
+    }
+
+    #[test]
+    fn small_hits_add_up_to_a_number() {
+        let mut app = app();
+        app.add_systems(Update, damage_numbers);
+        let target = app.world.spawn(Transform::default()).id();
+        let numbers = |app: &mut App| -> Vec<String> {
+            app.world
+                .query_filtered::<&Text, With<DamageNumber>>()
+                .iter(&app.world)
+                .map(|text| text.sections[0].value.clone())
+                .collect()
+        };
+
+        hit(&mut app, target, 0.2);
+        app.update();
+        hit(&mut app, target, 0.2);
+        app.update();
+        assert!(numbers(&mut app).is_empty());
+
+        hit(&mut app, target, 0.2);
+        app.update();
+        assert_eq!(numbers(&mut app), vec!["0.6".to_string()]);
+
+        // The tally starts over once it's been shown.
+        hit(&mut app, target, 0.2);
+        app.update();
+        assert_eq!(numbers(&mut app).len(), 1);
+    }
+}
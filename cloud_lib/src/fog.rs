@@ -48,6 +48,10 @@ pub struct FogMaterial {
     pub hexling_l: Vec3,
 }
 
+// How much fog a hexling clears around itself, as a multiplier on the usual radius.
+#[derive(Component, Debug)]
+pub struct FogReveal(pub f32);
+
 #[derive(Default, Resource)]
 pub struct HexlingFogTracker {
     pub hexling_entity_positions: HashMap<Entity, (Vec3, String)>,
@@ -95,7 +99,7 @@ pub fn the_function_that_dare_not_speak_its_name(
     }
 }
 
-fn init(
+pub fn init(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<FogMaterial>>,
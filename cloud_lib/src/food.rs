@@ -0,0 +1,124 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+    utils::HashSet,
+};
+use std::f32::consts::PI;
+
+use crate::{
+    classes::HexlingClass,
+    combat::Health,
+    economy::Stockpile,
+    hexling::Hexling,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    shapes::ShapeCache,
+    sound::SoundSettings,
+    GameState,
+};
+
+// They eat green triangles.
+pub const COLOR: Color = Color::rgb(0.2, 1.4, 0.3);
+const EAT_RANGE: f32 = 12.;
+// Hexlings below this fraction of their health are hungry, and hit half as hard.
+const HUNGRY: f32 = 0.5;
+const HUNGRY_DAMAGE: f32 = 0.5;
+// Matter a harvester gets out of each piece of food.
+const MATTER_PER_FOOD: f32 = 1.;
+const NOURISHMENT: f32 = 3.;
+const RADIUS: f32 = 5.;
+
+#[derive(Component)]
+pub struct Food {
+    pub nourishment: f32,
+}
+
+pub struct FoodPlugin;
+
+impl Plugin for FoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Over), crate::menu::despawn_thing::<Food>)
+            .add_systems(
+                Update,
+                (feed_hexlings, hunger).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+pub fn spawn_food(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    translation: Vec3,
+) {
+    commands.spawn((
+        Food {
+            nourishment: NOURISHMENT,
+        },
+        MaterialMesh2dBundle {
+            mesh: shapes.mesh(meshes, 3, RADIUS),
+            material: shapes.material(materials, COLOR),
+            transform: Transform::from_translation(translation)
+                .with_rotation(Quat::from_rotation_z(rand::random::<f32>() * 2. * PI)),
+            ..default()
+        },
+        Name::new("food"),
+    ));
+}
+
+// Hungry hexlings that bump into food eat it. Harvesters take it back to the stockpile instead,
+// hungry or not.
+pub fn feed_hexlings(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    food_query: Query<(Entity, &Food, &Transform)>,
+    mut hexling_query: Query<(Option<&HexlingClass>, &mut Health, &Transform), With<Hexling>>,
+    sound_settings: Res<SoundSettings>,
+    mut stockpile: ResMut<Stockpile>,
+) {
+    let mut eaten = HashSet::new();
+    for (class, mut health, transform) in hexling_query.iter_mut() {
+        let harvester = class == Some(&HexlingClass::Harvester);
+        if !harvester && health.current >= health.max {
+            continue;
+        }
+        for (entity, food, food_transform) in food_query.iter() {
+            if eaten.contains(&entity)
+                || (food_transform.translation - transform.translation).length() > EAT_RANGE
+            {
+                continue;
+            }
+            if harvester {
+                stockpile.matter += MATTER_PER_FOOD;
+            } else {
+                health.heal(food.nourishment);
+            }
+            eaten.insert(entity);
+            commands.entity(entity).despawn_recursive();
+            commands.spawn((AudioBundle {
+                source: asset_server.load("audio/tap.ogg"),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Once,
+                    volume: Volume::new_relative(sound_settings.effects_volume / 2.),
+                    ..default()
+                },
+            },));
+            break;
+        }
+    }
+}
+
+fn hunger(mut hexling_query: Query<(&Health, &mut StatModifiers), With<Hexling>>) {
+    for (health, mut modifiers) in hexling_query.iter_mut() {
+        if health.fraction() < HUNGRY {
+            modifiers.add(StatModifier::new(
+                Stat::BaseDamage,
+                ModifierOp::Mul(HUNGRY_DAMAGE),
+                "hunger",
+            ));
+        } else {
+            modifiers.remove("hunger");
+        }
+    }
+}
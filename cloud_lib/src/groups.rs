@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use crate::{
+    hexling::{Hexling, HexlingMode},
+    GameState,
+};
+
+pub const GROUP_COUNT: u8 = 5;
+const GROUP_KEYS: [KeyCode; GROUP_COUNT as usize] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+];
+const HUD_COLOR: Color = Color::rgba(1., 1., 1., 0.5);
+const HUD_SELECTED_COLOR: Color = Color::WHITE;
+const HUD_SIZE: f32 = 18.;
+
+// Which numbered group a hexling belongs to. Every hexling is in exactly one.
+#[derive(Component, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ControlGroup(pub u8);
+
+// The group commands go to, or the whole swarm if none is selected. Number keys select a group, 0
+// selects everything, and Ctrl plus a number moves the selection into that group.
+#[derive(Resource, Debug, Default)]
+pub struct SelectedGroup(pub Option<u8>);
+
+impl SelectedGroup {
+    pub fn contains(&self, group: &ControlGroup) -> bool {
+        self.0.is_none_or(|selected| selected == group.0)
+    }
+}
+
+#[derive(Component)]
+pub struct GroupHud;
+
+pub struct GroupsPlugin;
+
+impl Plugin for GroupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedGroup>()
+            .add_systems(OnEnter(GameState::Playing), spawn_hud.run_if(run_once()))
+            .add_systems(OnExit(GameState::Over), reset_selection)
+            .add_systems(
+                Update,
+                (group_controls, join_group, update_hud)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        GroupHud,
+        Name::new("group hud"),
+        TextBundle::default().with_style(Style {
+            left: Val::Px(12.),
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            ..default()
+        }),
+    ));
+}
+
+fn reset_selection(mut selected: ResMut<SelectedGroup>) {
+    *selected = SelectedGroup::default();
+}
+
+fn group_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut ControlGroup, With<Hexling>>,
+    mut selected: ResMut<SelectedGroup>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Key0) {
+        selected.0 = None;
+    }
+    let assigning = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for (i, key) in GROUP_KEYS.iter().enumerate() {
+        if !keyboard_input.just_pressed(*key) {
+            continue;
+        }
+        let group = i as u8 + 1;
+        if assigning {
+            for mut member in query.iter_mut() {
+                if selected.contains(&member) {
+                    *member = ControlGroup(group);
+                }
+            }
+        }
+        selected.0 = Some(group);
+    }
+}
+
+// Newborn hexlings join the selected group, and take up whatever it's doing.
+fn join_group(
+    mut commands: Commands,
+    new_query: Query<Entity, Added<Hexling>>,
+    query: Query<(&ControlGroup, &HexlingMode), With<Hexling>>,
+    selected: Res<SelectedGroup>,
+) {
+    for entity in new_query.iter() {
+        let group = ControlGroup(selected.0.unwrap_or(1));
+        let mode = query
+            .iter()
+            .find(|(member, _)| **member == group)
+            .map_or(HexlingMode::default(), |(_, mode)| *mode);
+        commands.entity(entity).insert((group, mode));
+    }
+}
+
+fn update_hud(
+    hexling_query: Query<&ControlGroup, With<Hexling>>,
+    mut query: Query<&mut Text, With<GroupHud>>,
+    selected: Res<SelectedGroup>,
+) {
+    let Ok(mut text) = query.get_single_mut() else {
+        return;
+    };
+    let mut counts = [0; GROUP_COUNT as usize];
+    for group in hexling_query.iter() {
+        if let Some(count) = counts.get_mut(group.0 as usize - 1) {
+            *count += 1;
+        }
+    }
+
+    let sections: Vec<TextSection> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let group = ControlGroup(i as u8 + 1);
+            let color = if selected.contains(&group) {
+                HUD_SELECTED_COLOR
+            } else {
+                HUD_COLOR
+            };
+            TextSection::new(
+                format!("{}: {}   ", group.0, count),
+                TextStyle {
+                    color,
+                    font_size: HUD_SIZE,
+                    ..default()
+                },
+            )
+        })
+        .collect();
+    // Rebuilding the text re-lays it out, so only do it when something changed.
+    let changed = text.sections.len() != sections.len()
+        || text
+            .sections
+            .iter()
+            .zip(sections.iter())
+            .any(|(a, b)| a.value != b.value || a.style.color != b.style.color);
+    if changed {
+        text.sections = sections;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<SelectedGroup>()
+            .add_systems(Update, (group_controls, join_group).chain());
+        app
+    }
+
+    // Presses the keys for a frame, then lets go of them.
+    fn press(app: &mut App, keys: &[KeyCode]) {
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        for key in keys {
+            input.press(*key);
+        }
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().reset_all();
+    }
+
+    fn selected(app: &App) -> Option<u8> {
+        app.world.resource::<SelectedGroup>().0
+    }
+
+    fn group(app: &App, entity: Entity) -> u8 {
+        app.world.get::<ControlGroup>(entity).unwrap().0
+    }
+
+    #[test]
+    fn number_keys_select_groups() {
+        let mut app = app();
+        press(&mut app, &[KeyCode::Key3]);
+        assert_eq!(selected(&app), Some(3));
+        press(&mut app, &[KeyCode::Key5]);
+        assert_eq!(selected(&app), Some(5));
+        press(&mut app, &[KeyCode::Key0]);
+        assert_eq!(selected(&app), None);
+    }
+
+    #[test]
+    fn ctrl_moves_the_selection_into_a_group() {
+        let mut app = app();
+        let first = app.world.spawn(Hexling).id();
+        let second = app.world.spawn(Hexling).id();
+        let other = app.world.spawn(Hexling).id();
+        // Let them hatch into the first group before moving one of them on.
+        app.update();
+        app.world.entity_mut(other).insert(ControlGroup(3));
+        press(&mut app, &[KeyCode::Key1]);
+        press(&mut app, &[KeyCode::ControlLeft, KeyCode::Key2]);
+
+        assert_eq!(selected(&app), Some(2));
+        assert_eq!(group(&app, first), 2);
+        assert_eq!(group(&app, second), 2);
+        assert_eq!(group(&app, other), 3);
+
+        // With everything selected, everything moves.
+        press(&mut app, &[KeyCode::Key0]);
+        press(&mut app, &[KeyCode::ControlRight, KeyCode::Key4]);
+        assert!([first, second, other]
+            .iter()
+            .all(|entity| group(&app, *entity) == 4));
+    }
+
+    #[test]
+    fn newborns_join_the_selected_group() {
+        let mut app = app();
+        app.world.spawn(Hexling);
+        let charging = app.world.spawn(Hexling).id();
+        app.update();
+        app.world
+            .entity_mut(charging)
+            .insert((ControlGroup(2), HexlingMode::Charging));
+        press(&mut app, &[KeyCode::Key2]);
+
+        let newborn = app.world.spawn(Hexling).id();
+        app.update();
+        assert_eq!(group(&app, newborn), 2);
+        assert_eq!(
+            app.world.get::<HexlingMode>(newborn),
+            Some(&HexlingMode::Charging)
+        );
+
+        // With nothing selected, they go to the first group.
+        press(&mut app, &[KeyCode::Key0]);
+        let newborn = app.world.spawn(Hexling).id();
+        app.update();
+        assert_eq!(group(&app, newborn), 1);
+        assert_eq!(
+            app.world.get::<HexlingMode>(newborn),
+            Some(&HexlingMode::Recalling)
+        );
+    }
+}
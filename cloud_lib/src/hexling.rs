@@ -6,24 +6,55 @@ use rand::prelude::Rng;
 use std::f32::consts::PI;
 
 use crate::{
+    aim::AimPoint,
     collision::Collider,
-    enemy::{CombatStats, Enemy},
-    fog::{Fog, FogMaterial, HexlingFogTracker},
-    map::{Source, Wall},
-    movement::{MovingEntityBundle, Velocity},
-    player::{events::SpawnHexlingEvent, HexlingState, Player},
+    combat::{Attacker, Health, Targeting},
+    damage::{DamageEvent, DamageKind, Interceptor},
+    enemy::Enemy,
+    fog::{
+        the_function_that_dare_not_speak_its_name, Fog, FogMaterial, FogReveal, HexlingFogTracker,
+    },
+    groups::{ControlGroup, SelectedGroup},
+    map::Source,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    movement::{Locomotion, LocomotionProfile, MovingEntityBundle, Velocity},
+    navigation::{FlowFieldCache, NavGrid},
+    orders::{OrderQueue, Unordered},
+    player::{
+        events::{ChargeEvent, RecallEvent, SpawnHexlingEvent},
+        Player,
+    },
+    shapes::ShapeCache,
     sound::SoundSettings,
+    status::StatusEffects,
+    steering::Boid,
+    veterancy::Veterancy,
 };
 
+// Each hexling is born dealing one of these, told apart by its shade of green.
+const HEXLING_DAMAGE_KINDS: [DamageKind; 3] = [
+    DamageKind::Kinetic,
+    DamageKind::Energy,
+    DamageKind::Corrosive,
+];
 const HEXLING_DETERIORATION_FACTOR: f32 = 0.1;
-const HEXLING_RADIUS: f32 = 6.;
 pub const HEXLING_SPEED: f32 = 200.;
 const MIN_PLAYER_DISTANCE: f32 = 65.;
 const MAX_PLAYER_DISTANCE: f32 = 85.;
+// Charging hexlings converge on the aim point, then fan out to within this distance of it.
+const SPREAD_RADIUS: f32 = 60.;
 
 #[derive(Component)]
 pub struct Hexling;
 
+// What a hexling was last told to do. Each control group can be doing something different.
+#[derive(Component, Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum HexlingMode {
+    #[default]
+    Recalling,
+    Charging,
+}
+
 pub struct HexlingPlugin;
 
 impl Plugin for HexlingPlugin {
@@ -31,21 +62,26 @@ impl Plugin for HexlingPlugin {
         app.add_systems(Update, hexling_spawner)
             .add_systems(
                 Update,
-                hexling_recall.run_if(in_state(HexlingState::Recalling)),
-            )
-            .add_systems(
-                Update,
-                hexling_charge.run_if(in_state(HexlingState::Charging)),
+                (
+                    switch_mode,
+                    (hexling_recall, hexling_charge, maintain_target_list),
+                    attack_target,
+                )
+                    .chain()
+                    .run_if(in_state(crate::GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (maintain_target_list, attack_target).run_if(in_state(HexlingState::Charging)),
+                hexling_deaths
+                    .after(crate::damage::apply_damage)
+                    .run_if(in_state(crate::GameState::Playing)),
             )
             .add_systems(OnExit(crate::GameState::Over), despawn_hexlings);
     }
 }
 
-fn hexling_spawner(
+#[allow(clippy::too_many_arguments)]
+pub fn hexling_spawner(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
     mut fog_materials: ResMut<Assets<FogMaterial>>,
@@ -56,6 +92,7 @@ fn hexling_spawner(
     mut ev_spawn_hexling: EventReader<SpawnHexlingEvent>,
     mut player_query: Query<&mut Transform, With<Player>>,
     mut query: Query<&mut EntropyComponent<ChaCha8Rng>, With<Source>>,
+    mut shapes: ResMut<ShapeCache>,
     sound_settings: Res<SoundSettings>,
 ) {
     let Ok(mut a_rng) = query.get_single_mut() else {
@@ -69,8 +106,8 @@ fn hexling_spawner(
     };
     let fog_material = fog_materials.get_mut(fog_handle).unwrap();
 
-    if !ev_spawn_hexling.is_empty() {
-        ev_spawn_hexling.clear();
+    if let Some(SpawnHexlingEvent(_, class)) = ev_spawn_hexling.read().last() {
+        let stats = class.stats();
 
         commands.spawn((AudioBundle {
             source: asset_server.load("audio/e2.ogg"),
@@ -81,12 +118,25 @@ fn hexling_spawner(
             },
         },));
 
-        // Various greens
-        let color = Color::rgb(
-            a_rng.gen_range(0.0..0.1),
-            a_rng.gen_range(0.7..1.0),
-            a_rng.gen_range(0.0..0.1),
-        );
+        // Various greens: bluish for energy, yellowish for corrosive, plain for kinetic.
+        let damage_kind = HEXLING_DAMAGE_KINDS[a_rng.gen_range(0..HEXLING_DAMAGE_KINDS.len())];
+        let color = match damage_kind {
+            DamageKind::Energy => Color::rgb(
+                a_rng.gen_range(0.0..0.1),
+                a_rng.gen_range(0.7..0.9),
+                a_rng.gen_range(0.4..0.6),
+            ),
+            DamageKind::Corrosive => Color::rgb(
+                a_rng.gen_range(0.4..0.6),
+                a_rng.gen_range(0.8..1.0),
+                a_rng.gen_range(0.0..0.1),
+            ),
+            _ => Color::rgb(
+                a_rng.gen_range(0.0..0.1),
+                a_rng.gen_range(0.7..1.0),
+                a_rng.gen_range(0.0..0.1),
+            ),
+        };
         let translation = Vec3::new(
             player_transform.translation.x
                 + a_rng.gen_range(MIN_PLAYER_DISTANCE..MAX_PLAYER_DISTANCE),
@@ -95,35 +145,51 @@ fn hexling_spawner(
             0.,
         );
         let shape = MaterialMesh2dBundle {
-            mesh: meshes
-                .add(shape::RegularPolygon::new(HEXLING_RADIUS, 6).into())
-                .into(),
-            material: materials.add(ColorMaterial::from(color)),
+            mesh: shapes.mesh(&mut meshes, 6, stats.radius),
+            material: shapes.material(&mut materials, color),
             transform: Transform::from_translation(translation)
                 .with_rotation(Quat::from_rotation_z(a_rng.gen_range(0.0..PI))),
             ..default()
         };
-        let entity = commands
-            .spawn((
-                MovingEntityBundle {
-                    collider: Collider::new(HEXLING_RADIUS),
-                    shape,
-                    velocity: Velocity::new(Vec3::ZERO),
-                },
-                Wall,
-                CombatStats {
-                    aggro_radius: 50.,
-                    attack_range: 10.,
-                    attack_rate: 1.,
-                    base_damage: 1.,
-                    cooldown: 0.,
-                    debris_despawn_timer: 0.,
-                    health: 10.,
-                    target_list: Vec::new(),
-                },
-            ))
-            .insert(Hexling)
-            .id();
+        // Class speed is a standing modifier, so anything else that speeds hexlings up stacks on it.
+        let mut modifiers = StatModifiers::default();
+        modifiers.add(StatModifier::new(
+            Stat::MoveSpeed,
+            ModifierOp::Mul(stats.move_speed),
+            "class",
+        ));
+        let mut entity = commands.spawn((
+            MovingEntityBundle {
+                collider: Collider::new(stats.radius),
+                shape,
+                velocity: Velocity::new(Vec3::ZERO),
+            },
+            Attacker {
+                attack_range: stats.attack_range,
+                attack_rate: 1.,
+                base_damage: stats.base_damage,
+                cooldown: 0.,
+                damage_kind,
+            },
+            Boid::default(),
+            FogReveal(stats.reveal),
+            Health::new(stats.health),
+            *class,
+            HexlingMode::default(),
+            Locomotion::new(LocomotionProfile::Hexling),
+            modifiers,
+            OrderQueue::default(),
+            Targeting::new(stats.aggro_radius),
+            Veterancy::default(),
+        ));
+        entity.insert(Hexling);
+        if let Some(radius) = stats.intercept_radius {
+            entity.insert(Interceptor { radius, share: 1. });
+        }
+        let entity = entity.id();
+
+        // The fog shader reads each hexling's reveal multiplier from its otherwise unused z.
+        let fog_position = translation.truncate().extend(stats.reveal);
 
         // Poke a hole in the fog of war at the hexling's new position
         // TODO: this hideous nightmare is only a gamejam mechanism to offset the fact I can't pass
@@ -131,62 +197,62 @@ fn hexling_spawner(
         // not supported on Web yet?
         // Thank goodness for neovim macros.
         if fog_material.hexling_a == Vec3::ZERO {
-            fog_material.hexling_a = translation;
+            fog_material.hexling_a = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "a".to_string()));
         } else if fog_material.hexling_b == Vec3::ZERO {
-            fog_material.hexling_b = translation;
+            fog_material.hexling_b = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "b".to_string()));
         } else if fog_material.hexling_c == Vec3::ZERO {
-            fog_material.hexling_c = translation;
+            fog_material.hexling_c = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "c".to_string()));
         } else if fog_material.hexling_d == Vec3::ZERO {
-            fog_material.hexling_d = translation;
+            fog_material.hexling_d = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "d".to_string()));
         } else if fog_material.hexling_e == Vec3::ZERO {
-            fog_material.hexling_e = translation;
+            fog_material.hexling_e = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "e".to_string()));
         } else if fog_material.hexling_f == Vec3::ZERO {
-            fog_material.hexling_f = translation;
+            fog_material.hexling_f = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "f".to_string()));
         } else if fog_material.hexling_g == Vec3::ZERO {
-            fog_material.hexling_g = translation;
+            fog_material.hexling_g = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "g".to_string()));
         } else if fog_material.hexling_h == Vec3::ZERO {
-            fog_material.hexling_h = translation;
+            fog_material.hexling_h = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "h".to_string()));
         } else if fog_material.hexling_i == Vec3::ZERO {
-            fog_material.hexling_i = translation;
+            fog_material.hexling_i = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "i".to_string()));
         } else if fog_material.hexling_j == Vec3::ZERO {
-            fog_material.hexling_j = translation;
+            fog_material.hexling_j = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "j".to_string()));
         } else if fog_material.hexling_k == Vec3::ZERO {
-            fog_material.hexling_k = translation;
+            fog_material.hexling_k = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "k".to_string()));
         } else if fog_material.hexling_l == Vec3::ZERO {
-            fog_material.hexling_l = translation;
+            fog_material.hexling_l = fog_position;
             fog_tracker
                 .hexling_entity_positions
                 .insert(entity, (translation, "l".to_string()));
@@ -194,46 +260,96 @@ fn hexling_spawner(
     }
 }
 
-fn hexling_recall(
-    mut hexling_query: Query<(&mut CombatStats, &Transform, &mut Velocity), With<Hexling>>,
+// Charge and recall only apply to the selected group.
+fn switch_mode(
+    mut ev_charge: EventReader<ChargeEvent>,
+    mut ev_recall: EventReader<RecallEvent>,
+    mut query: Query<(&ControlGroup, &mut HexlingMode), With<Hexling>>,
+    selected: Res<SelectedGroup>,
+) {
+    let mode = match (ev_charge.read().count(), ev_recall.read().count()) {
+        (0, 0) => return,
+        (_, 0) => HexlingMode::Charging,
+        _ => HexlingMode::Recalling,
+    };
+    for (group, mut hexling_mode) in query.iter_mut() {
+        if selected.contains(group) {
+            *hexling_mode = mode;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn hexling_recall(
+    mut flow_fields: ResMut<FlowFieldCache>,
+    mut hexling_query: Query<
+        (&HexlingMode, &mut Boid, &mut Targeting, &Transform),
+        (With<Hexling>, Unordered),
+    >,
+    nav_grid: Res<NavGrid>,
     player_query: Query<&Transform, With<Player>>,
 ) {
     let Ok(player_transform) = player_query.get_single() else {
         return;
     };
-    for (mut stats, transform, mut velocity) in hexling_query.iter_mut() {
+    let field = flow_fields.get_or_build(&nav_grid, player_transform.translation);
+    for (mode, mut boid, mut targeting, transform) in hexling_query.iter_mut() {
+        if *mode != HexlingMode::Recalling {
+            continue;
+        }
         // Recalling hexlings don't attack anything (for now). Be a good power-up tho.
-        stats.target_list.clear();
+        targeting.target_list.clear();
 
-        let direction = player_transform.translation - transform.translation;
-        if direction.length() > MAX_PLAYER_DISTANCE {
-            velocity.value = direction.normalize() * HEXLING_SPEED;
-        } else {
-            velocity.value = Vec3::ZERO;
-        }
+        let heading =
+            nav_grid.flow_direction(field, transform.translation, player_transform.translation);
+        boid.seek(heading, player_transform.translation, MIN_PLAYER_DISTANCE);
     }
 }
 
-fn hexling_charge(
+#[allow(clippy::type_complexity)]
+pub fn hexling_charge(
+    aim: Res<AimPoint>,
     enemy_query: Query<&Transform, With<Enemy>>,
-    mut hexling_query: Query<(&CombatStats, &Transform, &mut Velocity), With<Hexling>>,
+    mut flow_fields: ResMut<FlowFieldCache>,
+    mut hexling_query: Query<
+        (Entity, &HexlingMode, &mut Boid, &Targeting, &Transform),
+        (With<Hexling>, Unordered),
+    >,
+    nav_grid: Res<NavGrid>,
     player_query: Query<&Transform, With<Player>>,
 ) {
     let Ok(player_transform) = player_query.get_single() else {
         return;
     };
-    for (stats, transform, mut velocity) in hexling_query.iter_mut() {
+    for (entity, mode, mut boid, targeting, transform) in hexling_query.iter_mut() {
+        if *mode != HexlingMode::Charging {
+            continue;
+        }
         let direction = player_transform.translation - transform.translation;
-        if stats.target_list.is_empty() {
-            velocity.value = -(direction.normalize() * HEXLING_SPEED);
-        } else {
-            let Ok(target_transform) =
-                enemy_query.get(stats.target_list.first().unwrap().to_owned())
-            else {
-                return;
+        if let Some(target) = targeting.primary() {
+            let Ok(target_transform) = enemy_query.get(target) else {
+                continue;
             };
-            let direction = target_transform.translation - transform.translation;
-            velocity.value = direction.normalize() * HEXLING_SPEED;
+            let field = flow_fields.get_or_build(&nav_grid, target_transform.translation);
+            let heading =
+                nav_grid.flow_direction(field, transform.translation, target_transform.translation);
+            boid.seek(heading, target_transform.translation, 0.);
+        } else if let Some(aim_point) = aim.point {
+            // Each hexling has its own spot around the aim point, so they fan out on arrival and
+            // pick up whatever targets are about.
+            let angle = entity.index() as f32 * 2.4;
+            let distance = SPREAD_RADIUS * (0.5 + 0.5 * (entity.index() as f32 * 0.618).fract());
+            let spot = aim_point + Vec3::new(angle.cos(), angle.sin(), 0.) * distance;
+            if (aim_point - transform.translation).length() > SPREAD_RADIUS {
+                let field = flow_fields.get_or_build(&nav_grid, aim_point);
+                let heading = nav_grid.flow_direction(field, transform.translation, aim_point);
+                boid.seek(heading, aim_point, 0.);
+            } else {
+                // Close enough to go straight there; one flow field per hexling would be a lot.
+                boid.seek(spot - transform.translation, spot, 0.);
+            }
+        } else {
+            boid.wander(-direction);
         }
     }
 }
@@ -241,43 +357,100 @@ fn hexling_charge(
 // In theory, this could be a generic system. For now, it's convenient to treat it separately for
 // hexlings as they have some rather particular behaviour (charge/recall). We also don't have to
 // care about the player in the target list.
+#[allow(clippy::type_complexity)]
 fn maintain_target_list(
-    enemy_query: Query<(Entity, &Transform), (With<Enemy>, Without<Hexling>)>,
-    mut query: Query<(&mut CombatStats, &Transform), With<Hexling>>,
+    enemy_query: Query<(Entity, &Health, &Transform), (With<Enemy>, Without<Hexling>)>,
+    mut query: Query<(&HexlingMode, &mut Targeting, &Transform), (With<Hexling>, Unordered)>,
 ) {
-    for (mut stats, transform) in query.iter_mut() {
-        for (enemy_entity, enemy_transform) in enemy_query.iter() {
+    for (mode, mut targeting, transform) in query.iter_mut() {
+        if *mode != HexlingMode::Charging {
+            continue;
+        }
+        // Forget anything that has died or been despawned since we picked it up.
+        targeting
+            .target_list
+            .retain(|target| enemy_query.get(*target).is_ok_and(|(_, h, _)| !h.is_dead()));
+
+        for (enemy_entity, enemy_health, enemy_transform) in enemy_query.iter() {
+            if enemy_health.is_dead() {
+                continue;
+            }
             let direction = transform.translation - enemy_transform.translation;
 
-            if direction.length() < stats.aggro_radius && !stats.target_list.contains(&enemy_entity)
+            if direction.length() < targeting.aggro_radius
+                && !targeting.target_list.contains(&enemy_entity)
             {
-                stats.target_list.push(enemy_entity);
+                targeting.target_list.push(enemy_entity);
             }
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn attack_target(
-    mut enemy_query: Query<(&mut CombatStats, &Transform), (With<Enemy>, Without<Hexling>)>,
-    mut query: Query<(&mut CombatStats, &Transform), With<Hexling>>,
+    enemy_query: Query<(&Health, &Transform), (With<Enemy>, Without<Hexling>)>,
+    mut ev_damage: EventWriter<DamageEvent>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Attacker,
+            Option<&StatusEffects>,
+            &mut Health,
+            &Targeting,
+            &Transform,
+        ),
+        With<Hexling>,
+    >,
     time: Res<Time>,
 ) {
-    for (mut stats, transform) in query.iter_mut() {
-        if stats.target_list.is_empty() {
+    for (entity, mut attacker, effects, mut health, targeting, transform) in query.iter_mut() {
+        if effects.is_some_and(|e| e.is_stunned()) {
             continue;
         }
-        let Ok((mut target_stats, target_transform)) =
-            enemy_query.get_mut(stats.target_list.first().unwrap().to_owned())
-        else {
-            return;
+        let Some(target) = targeting.primary() else {
+            continue;
+        };
+        let Ok((target_health, target_transform)) = enemy_query.get(target) else {
+            continue;
         };
         let distance = (transform.translation - target_transform.translation).length();
-        if stats.cooldown <= 0. && target_stats.health > 0. && distance < stats.attack_range {
-            target_stats.health -= stats.base_damage;
-            stats.cooldown = stats.attack_rate * time.delta_seconds();
-            stats.health -= HEXLING_DETERIORATION_FACTOR;
+        if attacker.cooldown <= 0. && !target_health.is_dead() && distance < attacker.attack_range {
+            ev_damage.send(DamageEvent {
+                target,
+                source: Some(entity),
+                amount: attacker.base_damage,
+                kind: attacker.damage_kind,
+            });
+            attacker.cooldown = attacker.attack_rate * time.delta_seconds();
+            health.current -= HEXLING_DETERIORATION_FACTOR;
         } else {
-            stats.cooldown -= time.delta_seconds();
+            attacker.cooldown -= time.delta_seconds();
+        }
+    }
+}
+
+// Clears away hexlings that have run out of health, and gives their patch of fog back.
+pub fn hexling_deaths(
+    mut commands: Commands,
+    mut fog_materials: ResMut<Assets<FogMaterial>>,
+    mut fog_tracker: ResMut<HexlingFogTracker>,
+    handle: Query<&Handle<FogMaterial>, With<Fog>>,
+    query: Query<(Entity, &Health), With<Hexling>>,
+) {
+    for (entity, health) in query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        let Some((_, slot)) = fog_tracker.hexling_entity_positions.remove(&entity) else {
+            continue;
+        };
+        if let Some(fog_material) = handle
+            .get_single()
+            .ok()
+            .and_then(|handle| fog_materials.get_mut(handle))
+        {
+            the_function_that_dare_not_speak_its_name(fog_material, &Vec3::ZERO, &slot);
         }
     }
 }
@@ -1,18 +1,40 @@
 use bevy::prelude::*;
 
+pub mod ability;
+pub mod aim;
 pub mod camera;
+pub mod classes;
 pub mod collision;
+pub mod combat;
+pub mod damage;
+pub mod economy;
 pub mod enemy;
+pub mod feedback;
 pub mod fog;
+pub mod food;
+pub mod groups;
 pub mod hexling;
 pub mod map;
 pub mod menu;
+pub mod modifiers;
 pub mod movement;
+pub mod navigation;
+pub mod nest;
+pub mod orders;
 pub mod over_menu;
+pub mod particles;
 pub mod pause_menu;
 pub mod player;
 pub mod reset;
+pub mod sacrifice;
+pub mod shapes;
 pub mod sound;
+pub mod status;
+pub mod steering;
+pub mod story;
+pub mod swarm;
+pub mod upgrades;
+pub mod veterancy;
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
@@ -5,11 +5,16 @@ use rand::prelude::Rng;
 use std::f32::consts::PI;
 
 use crate::collision::Collider;
+use crate::nest::NestSites;
+use crate::shapes::ShapeCache;
+use crate::status::spawn_trap;
 
 const BASE_COLOR_LOW_END: f32 = 0.3;
 const BASE_COLOR_HIGH_END: f32 = 0.5;
+const NESTS_PER_ROOM: usize = 2;
 const ROOM_WIDTH: f32 = 400.;
 const ROOM_HEIGHT: f32 = 200.;
+const TRAPS_PER_ROOM: usize = 2;
 const WALL_RADIUS: f32 = 9.;
 const WARMTH_LOW_END: f32 = 0.4;
 const WARMTH_HIGH_END: f32 = 0.6;
@@ -52,17 +57,19 @@ fn prng_setup(mut commands: Commands, mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>
     commands.spawn((rng.fork_rng(), Source));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_room(
-    mut commands: Commands,
-    mut a_rng: Mut<'_, EntropyComponent<ChaCha8Rng>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    commands: &mut Commands,
+    a_rng: &mut EntropyComponent<ChaCha8Rng>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
     height: f32,
     width: f32,
     origin: Vec3,
     exits: Vec<ExitDirection>,
 ) {
-    let mut v = origin.clone();
+    let mut v = origin;
     let x_side = (width / WALL_RADIUS) as i32;
     let y_side = (height / WALL_RADIUS) as i32;
 
@@ -77,10 +84,8 @@ fn generate_room(
         let color = Color::rgb(warmth, base_color, base_color);
         commands.spawn((
             MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::RegularPolygon::new(WALL_RADIUS, 4).into())
-                    .into(),
-                material: materials.add(ColorMaterial::from(color)),
+                mesh: shapes.mesh(meshes, 4, WALL_RADIUS),
+                material: shapes.material(materials, color),
                 transform: Transform::from_translation(v)
                     .with_rotation(Quat::from_rotation_z(a_rng.gen_range(0.0..PI))),
                 ..default()
@@ -102,10 +107,8 @@ fn generate_room(
         let color = Color::rgb(warmth, base_color, base_color);
         commands.spawn((
             MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::RegularPolygon::new(WALL_RADIUS, 4).into())
-                    .into(),
-                material: materials.add(ColorMaterial::from(color)),
+                mesh: shapes.mesh(meshes, 4, WALL_RADIUS),
+                material: shapes.material(materials, color),
                 transform: Transform::from_translation(v)
                     .with_rotation(Quat::from_rotation_z(a_rng.gen_range(0.0..PI))),
                 ..default()
@@ -127,10 +130,8 @@ fn generate_room(
         let color = Color::rgb(warmth, base_color, base_color);
         commands.spawn((
             MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::RegularPolygon::new(WALL_RADIUS, 4).into())
-                    .into(),
-                material: materials.add(ColorMaterial::from(color)),
+                mesh: shapes.mesh(meshes, 4, WALL_RADIUS),
+                material: shapes.material(materials, color),
                 transform: Transform::from_translation(v)
                     .with_rotation(Quat::from_rotation_z(a_rng.gen_range(0.0..PI))),
                 ..default()
@@ -152,10 +153,8 @@ fn generate_room(
         let color = Color::rgb(warmth, base_color, base_color);
         commands.spawn((
             MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::RegularPolygon::new(WALL_RADIUS, 4).into())
-                    .into(),
-                material: materials.add(ColorMaterial::from(color)),
+                mesh: shapes.mesh(meshes, 4, WALL_RADIUS),
+                material: shapes.material(materials, color),
                 transform: Transform::from_translation(v)
                     .with_rotation(Quat::from_rotation_z(a_rng.gen_range(0.0..PI))),
                 ..default()
@@ -167,13 +166,14 @@ fn generate_room(
     }
 }
 
-fn generate_level_map(
-    commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
+pub fn generate_level_map(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
     mut query: Query<&mut EntropyComponent<ChaCha8Rng>, With<Source>>,
+    mut shapes: ResMut<ShapeCache>,
 ) {
-    let Ok(a_rng) = query.get_single_mut() else {
+    let Ok(mut a_rng) = query.get_single_mut() else {
         return;
     };
 
@@ -187,7 +187,48 @@ fn generate_level_map(
         ExitDirection::West,
     ];
 
+    // Nests sit somewhere in the west and east thirds of the room, clear of the walls. Walls are
+    // laid at twice their radius apart, so the room spans twice its nominal size.
+    let span = Vec3::new(width * 2., -height * 2., 0.);
+    let sites = (0..NESTS_PER_ROOM)
+        .map(|i| {
+            let third = (i * 2) as f32 / 3.;
+            origin
+                + Vec3::new(
+                    span.x * (third + a_rng.gen_range(0.1..0.23)),
+                    span.y * a_rng.gen_range(0.25..0.75),
+                    0.,
+                )
+        })
+        .collect();
+    commands.insert_resource(NestSites(sites));
+
     generate_room(
-        commands, a_rng, meshes, materials, height, width, origin, exits,
+        &mut commands,
+        &mut a_rng,
+        &mut meshes,
+        &mut materials,
+        &mut shapes,
+        height,
+        width,
+        origin,
+        exits,
     );
+
+    // A tar pit or two, somewhere in the middle third of the room.
+    for _ in 0..TRAPS_PER_ROOM {
+        let site = origin
+            + Vec3::new(
+                span.x * a_rng.gen_range(0.4..0.6),
+                span.y * a_rng.gen_range(0.15..0.85),
+                0.,
+            );
+        spawn_trap(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut shapes,
+            site,
+        );
+    }
 }
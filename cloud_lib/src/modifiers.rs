@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    combat::{Attacker, Health, Targeting},
+    GameState,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Deserialize)]
+pub enum Stat {
+    AggroRadius,
+    AttackRange,
+    AttackRate,
+    BaseDamage,
+    MaxHealth,
+    MoveSpeed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum ModifierOp {
+    Add(f32),
+    Mul(f32),
+    // Ignores the base and every other modifier. The most recently added override wins.
+    Override(f32),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub op: ModifierOp,
+    // Who put this here: "hunger", "status", an upgrade's name and so on. A source holds at most one
+    // modifier per stat.
+    pub source: &'static str,
+    // Seconds left before the modifier lapses. `None` lasts until removed.
+    pub remaining: Option<f32>,
+}
+
+impl StatModifier {
+    pub fn new(stat: Stat, op: ModifierOp, source: &'static str) -> Self {
+        Self {
+            stat,
+            op,
+            source,
+            remaining: None,
+        }
+    }
+
+    pub fn timed(self, seconds: f32) -> Self {
+        Self {
+            remaining: Some(seconds),
+            ..self
+        }
+    }
+}
+
+// What an entity's stats are before anything modifies them. Captured from its `Attacker`, `Health`
+// and `Targeting` when it spawns; from then on those hold the effective values, recomputed every
+// tick.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BaseStats {
+    pub aggro_radius: f32,
+    pub attack_range: f32,
+    pub attack_rate: f32,
+    pub base_damage: f32,
+    pub max_health: f32,
+    // A multiplier on however fast the entity is otherwise trying to go.
+    pub move_speed: f32,
+}
+
+impl BaseStats {
+    pub fn get(&self, stat: Stat) -> f32 {
+        match stat {
+            Stat::AggroRadius => self.aggro_radius,
+            Stat::AttackRange => self.attack_range,
+            Stat::AttackRate => self.attack_rate,
+            Stat::BaseDamage => self.base_damage,
+            Stat::MaxHealth => self.max_health,
+            Stat::MoveSpeed => self.move_speed,
+        }
+    }
+}
+
+impl BaseStats {
+    fn capture(
+        attacker: Option<&Attacker>,
+        health: &Health,
+        targeting: Option<&Targeting>,
+    ) -> Self {
+        Self {
+            aggro_radius: targeting.map_or(0., |t| t.aggro_radius),
+            attack_range: attacker.map_or(0., |a| a.attack_range),
+            attack_rate: attacker.map_or(0., |a| a.attack_rate),
+            base_damage: attacker.map_or(0., |a| a.base_damage),
+            max_health: health.max,
+            move_speed: 1.,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct StatModifiers(Vec<StatModifier>);
+
+impl StatModifiers {
+    // Replaces any modifier the same source already has on the same stat.
+    pub fn add(&mut self, modifier: StatModifier) {
+        self.0
+            .retain(|m| m.source != modifier.source || m.stat != modifier.stat);
+        self.0.push(modifier);
+    }
+
+    pub fn remove(&mut self, source: &'static str) {
+        self.0.retain(|m| m.source != source);
+    }
+
+    // Additions are summed onto the base, then multipliers applied, unless something overrides.
+    pub fn apply(&self, stat: Stat, base: f32) -> f32 {
+        let modifiers = self.0.iter().filter(|m| m.stat == stat);
+        let mut add = 0.;
+        let mut mul = 1.;
+        let mut value = None;
+        for modifier in modifiers {
+            match modifier.op {
+                ModifierOp::Add(amount) => add += amount,
+                ModifierOp::Mul(factor) => mul *= factor,
+                ModifierOp::Override(amount) => value = Some(amount),
+            }
+        }
+        value.unwrap_or((base + add) * mul)
+    }
+}
+
+// The effective move speed multiplier, as computed from `BaseStats::move_speed` and its modifiers.
+#[derive(Component, Debug)]
+pub struct MoveSpeed(pub f32);
+
+pub struct ModifierPlugin;
+
+impl Plugin for ModifierPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (capture_base_stats, expire_modifiers, apply_modifiers)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn capture_base_stats(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            Option<&Attacker>,
+            &Health,
+            Option<&Targeting>,
+            Has<StatModifiers>,
+        ),
+        Without<BaseStats>,
+    >,
+) {
+    for (entity, attacker, health, targeting, has_modifiers) in query.iter() {
+        let mut entity = commands.entity(entity);
+        entity.insert((
+            BaseStats::capture(attacker, health, targeting),
+            MoveSpeed(1.),
+        ));
+        // Some things are born with modifiers already.
+        if !has_modifiers {
+            entity.insert(StatModifiers::default());
+        }
+    }
+}
+
+fn expire_modifiers(mut query: Query<&mut StatModifiers>, time: Res<Time>) {
+    for mut modifiers in query.iter_mut() {
+        if modifiers.0.iter().all(|m| m.remaining.is_none()) {
+            continue;
+        }
+        for modifier in modifiers.0.iter_mut() {
+            if let Some(remaining) = modifier.remaining.as_mut() {
+                *remaining -= time.delta_seconds();
+            }
+        }
+        modifiers.0.retain(|m| m.remaining.is_none_or(|r| r > 0.));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_modifiers(
+    mut query: Query<(
+        Option<&mut Attacker>,
+        &BaseStats,
+        &mut Health,
+        &StatModifiers,
+        &mut MoveSpeed,
+        Option<&mut Targeting>,
+    )>,
+) {
+    for (attacker, base, mut health, modifiers, mut move_speed, targeting) in query.iter_mut() {
+        let value = |stat| modifiers.apply(stat, base.get(stat));
+        if let Some(mut attacker) = attacker {
+            attacker.attack_range = value(Stat::AttackRange);
+            attacker.attack_rate = value(Stat::AttackRate);
+            attacker.base_damage = value(Stat::BaseDamage);
+        }
+        if let Some(mut targeting) = targeting {
+            targeting.aggro_radius = value(Stat::AggroRadius);
+        }
+        // New capacity arrives already filled; lost capacity takes current health down with it.
+        let max_health = value(Stat::MaxHealth);
+        if max_health != health.max {
+            let gained = (max_health - health.max).max(0.);
+            health.max = max_health;
+            health.current = (health.current + gained).min(max_health);
+        }
+        move_speed.0 = value(Stat::MoveSpeed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_stack_then_override() {
+        let mut modifiers = StatModifiers::default();
+        modifiers.add(StatModifier::new(
+            Stat::BaseDamage,
+            ModifierOp::Add(1.),
+            "upgrade",
+        ));
+        modifiers.add(StatModifier::new(
+            Stat::BaseDamage,
+            ModifierOp::Mul(0.5),
+            "hunger",
+        ));
+        assert_eq!(modifiers.apply(Stat::BaseDamage, 3.), 2.);
+        assert_eq!(modifiers.apply(Stat::AttackRate, 3.), 3.);
+
+        // Same source, same stat: the old modifier is replaced rather than stacked.
+        modifiers.add(StatModifier::new(
+            Stat::BaseDamage,
+            ModifierOp::Add(3.),
+            "upgrade",
+        ));
+        assert_eq!(modifiers.apply(Stat::BaseDamage, 3.), 3.);
+
+        modifiers.add(StatModifier::new(
+            Stat::BaseDamage,
+            ModifierOp::Override(10.),
+            "relic",
+        ));
+        assert_eq!(modifiers.apply(Stat::BaseDamage, 3.), 10.);
+
+        modifiers.remove("relic");
+        modifiers.remove("hunger");
+        assert_eq!(modifiers.apply(Stat::BaseDamage, 3.), 6.);
+    }
+
+    #[test]
+    fn max_health_modifiers_fill_and_drain() {
+        let mut app = App::new();
+        app.add_systems(Update, (capture_base_stats, apply_modifiers).chain());
+        let mut health = Health::new(10.);
+        health.current = 6.;
+        let entity = app.world.spawn(health).id();
+        app.update();
+
+        app.world
+            .get_mut::<StatModifiers>(entity)
+            .unwrap()
+            .add(StatModifier::new(
+                Stat::MaxHealth,
+                ModifierOp::Add(5.),
+                "upgrade",
+            ));
+        app.update();
+        let health = app.world.get::<Health>(entity).unwrap();
+        assert_eq!((health.current, health.max), (11., 15.));
+
+        app.world
+            .get_mut::<StatModifiers>(entity)
+            .unwrap()
+            .remove("upgrade");
+        app.update();
+        let health = app.world.get::<Health>(entity).unwrap();
+        assert_eq!((health.current, health.max), (10., 10.));
+    }
+}
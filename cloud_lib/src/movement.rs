@@ -5,21 +5,109 @@ use std::f32::consts::PI;
 use crate::{
     collision::Collider,
     enemy::Debris,
-    fog::{the_function_that_dare_not_speak_its_name, Fog, FogMaterial, HexlingFogTracker},
-    hexling::Hexling,
+    feedback::Flash,
+    fog::{
+        the_function_that_dare_not_speak_its_name, Fog, FogMaterial, FogReveal, HexlingFogTracker,
+    },
+    hexling::{Hexling, HEXLING_SPEED},
+    modifiers::MoveSpeed,
     player::events::{ChargeEvent, RecallEvent, SpawnHexlingEvent},
-    player::{Player, CHARGE_COLOR, RECALL_COLOR},
+    player::{Player, CHARGE_COLOR, RECALL_COLOR, SPEED},
+    shapes::ShapeCache,
     GameState,
 };
 
+// Fraction of an impulse that remains after a second.
+const IMPULSE_DECAY: f32 = 0.005;
+
 #[derive(Component, Debug)]
 pub struct Velocity {
     pub value: Vec3,
+    // A shove from outside (a knockback, say) on top of wherever the entity is trying to go. Decays
+    // to nothing over time.
+    pub impulse: Vec3,
 }
 
 impl Velocity {
     pub fn new(value: Vec3) -> Self {
-        Self { value }
+        Self {
+            value,
+            impulse: Vec3::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LocomotionProfile {
+    Enemy,
+    Hexling,
+    Player,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LocomotionParams {
+    // Units per second squared, while trying to move.
+    pub acceleration: f32,
+    // Units per second squared, while trying to stop.
+    pub friction: f32,
+    pub max_speed: f32,
+}
+
+// Shared by everything that moves under its own power. Tweak at runtime to change how every entity
+// of a profile handles.
+#[derive(Resource)]
+pub struct LocomotionSettings {
+    pub enemy: LocomotionParams,
+    pub hexling: LocomotionParams,
+    pub player: LocomotionParams,
+}
+
+impl LocomotionSettings {
+    pub fn get(&self, profile: LocomotionProfile) -> &LocomotionParams {
+        match profile {
+            LocomotionProfile::Enemy => &self.enemy,
+            LocomotionProfile::Hexling => &self.hexling,
+            LocomotionProfile::Player => &self.player,
+        }
+    }
+}
+
+impl Default for LocomotionSettings {
+    fn default() -> Self {
+        Self {
+            enemy: LocomotionParams {
+                acceleration: 400.,
+                friction: 400.,
+                max_speed: 100.,
+            },
+            hexling: LocomotionParams {
+                acceleration: 3000.,
+                friction: 3000.,
+                max_speed: HEXLING_SPEED,
+            },
+            player: LocomotionParams {
+                acceleration: 1600.,
+                friction: 1200.,
+                max_speed: SPEED,
+            },
+        }
+    }
+}
+
+// Steers `Velocity` toward `intent`, within the limits of the entity's profile.
+#[derive(Component, Debug)]
+pub struct Locomotion {
+    // The velocity the entity would like to have.
+    pub intent: Vec3,
+    pub profile: LocomotionProfile,
+}
+
+impl Locomotion {
+    pub fn new(profile: LocomotionProfile) -> Self {
+        Self {
+            intent: Vec3::ZERO,
+            profile,
+        }
     }
 }
 
@@ -34,10 +122,12 @@ pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<LocomotionSettings>().add_systems(
             Update,
             (
-                update_position.run_if(in_state(GameState::Playing)),
+                (locomotion, update_position)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
                 flip_player.run_if(in_state(GameState::Playing)),
                 spin_player.run_if(in_state(GameState::Playing)),
                 player_debris.run_if(in_state(GameState::Over)),
@@ -46,12 +136,32 @@ impl Plugin for MovementPlugin {
     }
 }
 
+pub fn locomotion(
+    mut query: Query<(&Locomotion, &mut Velocity)>,
+    settings: Res<LocomotionSettings>,
+    time: Res<Time>,
+) {
+    for (locomotion, mut velocity) in query.iter_mut() {
+        let params = settings.get(locomotion.profile);
+        let target = locomotion.intent.clamp_length_max(params.max_speed);
+        let rate = if target == Vec3::ZERO {
+            params.friction
+        } else {
+            params.acceleration
+        };
+        // Anything else that has pushed us about (walls, mostly) can't push us past max speed.
+        let current = velocity.value.clamp_length_max(params.max_speed);
+        let change = target - current;
+        velocity.value = current + change.clamp_length_max(rate * time.delta_seconds());
+    }
+}
+
 fn update_position(
     mut fog_tracker: ResMut<HexlingFogTracker>,
     mut handle: Query<&Handle<FogMaterial>, With<Fog>>,
-    hexling_query: Query<&Hexling>,
+    hexling_query: Query<&FogReveal, With<Hexling>>,
     mut materials: ResMut<Assets<FogMaterial>>,
-    mut query: Query<(Entity, &Velocity, &mut Transform)>,
+    mut query: Query<(Entity, &mut Velocity, &mut Transform, Option<&MoveSpeed>)>,
     time: Res<Time>,
 ) {
     let Ok(fog_handle) = handle.get_single_mut() else {
@@ -59,16 +169,24 @@ fn update_position(
     };
     let fog_material = materials.get_mut(fog_handle).unwrap();
 
-    for (entity, velocity, mut transform) in query.iter_mut() {
-        transform.translation += velocity.value * time.delta_seconds();
+    for (entity, mut velocity, mut transform, move_speed) in query.iter_mut() {
+        let speed = move_speed.map_or(1., |s| s.0);
+        transform.translation += (velocity.value * speed + velocity.impulse) * time.delta_seconds();
+        if velocity.impulse != Vec3::ZERO {
+            velocity.impulse *= IMPULSE_DECAY.powf(time.delta_seconds());
+            if velocity.impulse.length_squared() < 1. {
+                velocity.impulse = Vec3::ZERO;
+            }
+        }
 
         // TODO: hideous jamstrousity.
-        if hexling_query.get(entity).is_ok() {
+        if let Ok(reveal) = hexling_query.get(entity) {
             let Some((_, initial)) = fog_tracker
                 .hexling_entity_positions
                 .get::<Entity>(&entity.to_owned())
             else {
-                return;
+                // The fog only has so many slots. Hexlings past the last one clear none of it.
+                continue;
             };
             let s1 = String::from(initial);
             let s2 = String::from(initial);
@@ -78,23 +196,44 @@ fn update_position(
 
             the_function_that_dare_not_speak_its_name(
                 fog_material,
-                &transform.translation,
-                &String::from(s2),
+                &transform.translation.truncate().extend(reveal.0),
+                &s2,
             )
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn flip_player(
     mut animations: ResMut<Assets<AnimationClip>>,
     mut ev_charge: EventReader<ChargeEvent>,
     mut ev_recall: EventReader<RecallEvent>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut query: Query<(&mut AnimationPlayer, Entity, &Handle<ColorMaterial>, &Name), With<Player>>,
+    mut query: Query<
+        (
+            &mut AnimationPlayer,
+            Entity,
+            Option<&mut Flash>,
+            &mut Handle<ColorMaterial>,
+            &Name,
+        ),
+        With<Player>,
+    >,
+    mut shapes: ResMut<ShapeCache>,
 ) {
-    let Ok((mut animation_player, _entity, material_handle, name)) = query.get_single_mut() else {
+    let Ok((mut animation_player, _entity, mut flash, mut material_handle, name)) =
+        query.get_single_mut()
+    else {
         return;
     };
+    // The player's material is shared, so recolour by swapping it, under any flash.
+    let mut recolor = |color: Color| {
+        let handle = shapes.material(&mut materials, color);
+        match flash.as_mut() {
+            Some(flash) => flash.restore = handle,
+            None => *material_handle = handle,
+        }
+    };
 
     for _ in ev_charge.read() {
         let mut animation = AnimationClip::default();
@@ -114,9 +253,7 @@ fn flip_player(
         );
         let animation_handle = animations.add(animation);
         animation_player.play(animation_handle);
-        if let Some(material) = materials.get_mut(material_handle) {
-            material.color = CHARGE_COLOR;
-        }
+        recolor(CHARGE_COLOR);
     }
 
     for _ in ev_recall.read() {
@@ -137,9 +274,7 @@ fn flip_player(
         );
         let animation_handle = animations.add(animation);
         animation_player.play(animation_handle);
-        if let Some(material) = materials.get_mut(material_handle) {
-            material.color = RECALL_COLOR;
-        }
+        recolor(RECALL_COLOR);
     }
 }
 
@@ -180,3 +315,77 @@ fn player_debris(mut query: Query<(&Velocity, &mut Transform), With<Debris>>, ti
         transform.translation += velocity.value * time.delta_seconds();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Duration;
+
+    use super::*;
+
+    #[test]
+    fn hexlings_without_a_fog_slot_hold_nothing_up() {
+        let mut app = App::new();
+        app.init_resource::<Assets<FogMaterial>>()
+            .init_resource::<HexlingFogTracker>()
+            .init_resource::<Time>()
+            .add_systems(Update, update_position);
+        let fog = app
+            .world
+            .resource_mut::<Assets<FogMaterial>>()
+            .add(FogMaterial {
+                alpha_mode: AlphaMode::Blend,
+                light_radius: 500.,
+                player_position: Vec3::ZERO,
+                hexling_a: Vec3::ZERO,
+                hexling_b: Vec3::ZERO,
+                hexling_c: Vec3::ZERO,
+                hexling_d: Vec3::ZERO,
+                hexling_e: Vec3::ZERO,
+                hexling_f: Vec3::ZERO,
+                hexling_g: Vec3::ZERO,
+                hexling_h: Vec3::ZERO,
+                hexling_i: Vec3::ZERO,
+                hexling_j: Vec3::ZERO,
+                hexling_k: Vec3::ZERO,
+                hexling_l: Vec3::ZERO,
+            });
+        app.world.spawn((Fog, fog));
+
+        // More hexlings than the fog has slots for: the last one goes without.
+        for (i, slot) in "abcdefghijklm".chars().enumerate() {
+            let hexling = app
+                .world
+                .spawn((
+                    FogReveal(1.),
+                    Hexling,
+                    Transform::default(),
+                    Velocity::new(Vec3::X),
+                ))
+                .id();
+            if i < 12 {
+                app.world
+                    .resource_mut::<HexlingFogTracker>()
+                    .hexling_entity_positions
+                    .insert(hexling, (Vec3::ZERO, slot.to_string()));
+            }
+        }
+        let player = app
+            .world
+            .spawn((Player, Transform::default(), Velocity::new(Vec3::Y)))
+            .id();
+
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Transform>(player).unwrap().translation,
+            Vec3::Y
+        );
+        let mut hexlings = app.world.query_filtered::<&Transform, With<Hexling>>();
+        assert!(hexlings
+            .iter(&app.world)
+            .all(|transform| transform.translation == Vec3::X));
+    }
+}
@@ -0,0 +1,434 @@
+use bevy::{prelude::*, utils::HashMap};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::map::Wall;
+
+// Matches the spacing of wall tiles laid down by `map::generate_room`.
+pub const CELL_SIZE: f32 = 18.;
+// Extra distance kept between a wall tile's centre and anything walking past it.
+const CLEARANCE: f32 = 18.;
+// Open cells added around the outermost walls, so agents can path around the outside of a room.
+const MARGIN_CELLS: i32 = 6;
+// How often an agent re-runs A* while its target is out of sight.
+const REPATH_SECONDS: f32 = 0.5;
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+// A coarse occupancy grid built from the walls of the generated map. Anything outside the grid is
+// considered open space.
+#[derive(Default, Resource)]
+pub struct NavGrid {
+    origin: Vec2,
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+}
+
+// Per-entity A* state. The path is cached and only recalculated every `REPATH_SECONDS`, or when the
+// goal wanders off.
+#[derive(Component, Default)]
+pub struct NavAgent {
+    goal: Vec3,
+    path: Vec<Vec3>,
+    repath_timer: f32,
+}
+
+// Distance-to-goal for every cell, shared by every hexling heading for the same place.
+pub struct FlowField {
+    goal: IVec2,
+    costs: Vec<Option<u32>>,
+}
+
+// Flow fields are cheap enough to build on demand, but a swarm of hexlings will all ask for the same
+// goal in a frame. Cleared at the start of each frame since goals (the player, enemies) move.
+#[derive(Default, Resource)]
+pub struct FlowFieldCache {
+    fields: HashMap<IVec2, FlowField>,
+}
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .init_resource::<FlowFieldCache>()
+            .add_systems(PreUpdate, (rebuild_nav_grid, clear_flow_fields).chain());
+    }
+}
+
+impl NavGrid {
+    pub fn from_walls<I: IntoIterator<Item = Vec3>>(walls: I) -> Self {
+        let walls: Vec<Vec2> = walls.into_iter().map(|w| w.truncate()).collect();
+        if walls.is_empty() {
+            return Self::default();
+        }
+
+        let (min, max) = walls.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), w| (min.min(*w), max.max(*w)),
+        );
+        let margin = CELL_SIZE * MARGIN_CELLS as f32;
+        let origin = min - Vec2::splat(margin);
+        let size = ((max + Vec2::splat(margin) - origin) / CELL_SIZE).ceil();
+        let mut grid = Self {
+            origin,
+            width: size.x as i32 + 1,
+            height: size.y as i32 + 1,
+            blocked: vec![],
+        };
+        grid.blocked = vec![false; (grid.width * grid.height) as usize];
+
+        let reach = CLEARANCE;
+        let reach_cells = (reach / CELL_SIZE).ceil() as i32;
+        for wall in walls.iter() {
+            let centre = grid.clamp_cell(grid.cell_of(wall.extend(0.)));
+            for dx in -reach_cells..=reach_cells {
+                for dy in -reach_cells..=reach_cells {
+                    let cell = centre + IVec2::new(dx, dy);
+                    if !grid.in_bounds(cell) {
+                        continue;
+                    }
+                    if grid.cell_to_world(cell).truncate().distance(*wall) < reach {
+                        let index = grid.index(cell);
+                        grid.blocked[index] = true;
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty()
+    }
+
+    pub fn cell_to_world(&self, cell: IVec2) -> Vec3 {
+        (self.origin + (cell.as_vec2() + Vec2::splat(0.5)) * CELL_SIZE).extend(0.)
+    }
+
+    // Cells outside the grid are open space.
+    pub fn is_walkable(&self, cell: IVec2) -> bool {
+        !self.in_bounds(cell) || !self.blocked[self.index(cell)]
+    }
+
+    pub fn is_walkable_at(&self, pos: Vec3) -> bool {
+        self.is_walkable(self.cell_of(pos))
+    }
+
+    // Walks the cells between two points, so we can skip pathfinding entirely when nothing is in
+    // the way.
+    pub fn line_of_sight(&self, from: Vec3, to: Vec3) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let delta = (to - from).truncate();
+        let steps = (delta.length() / (CELL_SIZE / 2.)).ceil() as i32;
+        (1..=steps).all(|step| {
+            let point = from.truncate() + delta * (step as f32 / steps as f32);
+            self.is_walkable(self.cell_of(point.extend(0.)))
+        })
+    }
+
+    pub fn find_path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        if self.is_empty() {
+            return Some(vec![to]);
+        }
+        let start = self.clamp_cell(self.cell_of(from));
+        let goal = self.clamp_cell(self.cell_of(to));
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+        let mut g_score: HashMap<IVec2, u32> = HashMap::new();
+        g_score.insert(start, 0);
+        open.push(Reverse((octile(start, goal), start.x, start.y)));
+
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let current = IVec2::new(x, y);
+            if current == goal {
+                let mut path = vec![to];
+                let mut cell = current;
+                while let Some(previous) = came_from.get(&cell) {
+                    if *previous != start {
+                        path.push(self.cell_to_world(*previous));
+                    }
+                    cell = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let current_g = g_score[&current];
+            for (neighbour, cost) in self.neighbours(current, goal) {
+                let tentative = current_g + cost;
+                if tentative < *g_score.get(&neighbour).unwrap_or(&u32::MAX) {
+                    came_from.insert(neighbour, current);
+                    g_score.insert(neighbour, tentative);
+                    open.push(Reverse((
+                        tentative + octile(neighbour, goal),
+                        neighbour.x,
+                        neighbour.y,
+                    )));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn flow_field(&self, goal: Vec3) -> FlowField {
+        let goal = self.clamp_cell(self.cell_of(goal));
+        let mut costs = vec![None; self.blocked.len()];
+        if self.is_empty() {
+            return FlowField { goal, costs };
+        }
+
+        // Plain Dijkstra outward from the goal.
+        let mut open = BinaryHeap::new();
+        costs[self.index(goal)] = Some(0);
+        open.push(Reverse((0, goal.x, goal.y)));
+        while let Some(Reverse((cost, x, y))) = open.pop() {
+            let current = IVec2::new(x, y);
+            if costs[self.index(current)].is_some_and(|c| c < cost) {
+                continue;
+            }
+            for (neighbour, step) in self.neighbours(current, goal) {
+                let index = self.index(neighbour);
+                let tentative = cost + step;
+                if costs[index].is_none_or(|c| tentative < c) {
+                    costs[index] = Some(tentative);
+                    open.push(Reverse((tentative, neighbour.x, neighbour.y)));
+                }
+            }
+        }
+
+        FlowField { goal, costs }
+    }
+
+    // Normalised direction for something at `from` that wants to reach `to`, following the flow
+    // field when a wall is in the way.
+    pub fn flow_direction(&self, field: &FlowField, from: Vec3, to: Vec3) -> Vec3 {
+        if self.line_of_sight(from, to) {
+            return (to - from).normalize_or_zero();
+        }
+
+        let cell = self.cell_of(from);
+        if !self.in_bounds(cell) {
+            // Outside the grid everything is open, so head back in toward the goal.
+            return (to - from).normalize_or_zero();
+        }
+
+        let best = NEIGHBOURS
+            .iter()
+            .map(|offset| cell + *offset)
+            .filter(|c| self.in_bounds(*c))
+            .filter_map(|c| field.costs[self.index(c)].map(|cost| (cost, c)))
+            .min_by_key(|(cost, _)| *cost);
+        match best {
+            Some((_, next)) if next != field.goal => {
+                (self.cell_to_world(next) - from).normalize_or_zero()
+            }
+            _ => (to - from).normalize_or_zero(),
+        }
+    }
+
+    fn cell_of(&self, pos: Vec3) -> IVec2 {
+        ((pos.truncate() - self.origin) / CELL_SIZE)
+            .floor()
+            .as_ivec2()
+    }
+
+    fn clamp_cell(&self, cell: IVec2) -> IVec2 {
+        if self.is_empty() {
+            return IVec2::ZERO;
+        }
+        cell.clamp(IVec2::ZERO, IVec2::new(self.width - 1, self.height - 1))
+    }
+
+    fn in_bounds(&self, cell: IVec2) -> bool {
+        cell.x >= 0 && cell.y >= 0 && cell.x < self.width && cell.y < self.height
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    // Walkable neighbouring cells and the cost of stepping to them. Diagonals can't cut the corner
+    // of a blocked cell. The goal always counts as walkable, since targets like to hug walls.
+    fn neighbours(&self, cell: IVec2, goal: IVec2) -> impl Iterator<Item = (IVec2, u32)> + '_ {
+        let walkable =
+            move |c: IVec2| self.in_bounds(c) && (c == goal || !self.blocked[self.index(c)]);
+        NEIGHBOURS.iter().filter_map(move |offset| {
+            let next = cell + *offset;
+            if !walkable(next) {
+                return None;
+            }
+            if offset.x != 0 && offset.y != 0 {
+                if !walkable(cell + IVec2::new(offset.x, 0))
+                    || !walkable(cell + IVec2::new(0, offset.y))
+                {
+                    return None;
+                }
+                return Some((next, DIAGONAL_COST));
+            }
+            Some((next, STRAIGHT_COST))
+        })
+    }
+}
+
+impl NavAgent {
+    // Normalised direction toward `to`, routing around walls with A*.
+    pub fn direction(&mut self, grid: &NavGrid, from: Vec3, to: Vec3, delta_seconds: f32) -> Vec3 {
+        if grid.line_of_sight(from, to) {
+            self.path.clear();
+            return (to - from).normalize_or_zero();
+        }
+
+        self.repath_timer -= delta_seconds;
+        if self.path.is_empty()
+            || self.repath_timer <= 0.
+            || self.goal.distance(to) > CELL_SIZE * 2.
+        {
+            self.path = grid.find_path(from, to).unwrap_or_default();
+            self.goal = to;
+            self.repath_timer = REPATH_SECONDS;
+        }
+
+        // Drop waypoints we've reached, and skip ahead to any we can already see.
+        while let Some(next) = self.path.first() {
+            let reached = next.distance(from) < CELL_SIZE / 2.;
+            let can_skip = self.path.len() > 1 && grid.line_of_sight(from, self.path[1]);
+            if reached || can_skip {
+                self.path.remove(0);
+            } else {
+                break;
+            }
+        }
+
+        match self.path.first() {
+            Some(waypoint) => (*waypoint - from).normalize_or_zero(),
+            None => (to - from).normalize_or_zero(),
+        }
+    }
+}
+
+impl FlowFieldCache {
+    pub fn get_or_build(&mut self, grid: &NavGrid, goal: Vec3) -> &FlowField {
+        let key = grid.clamp_cell(grid.cell_of(goal));
+        self.fields
+            .entry(key)
+            .or_insert_with(|| grid.flow_field(goal))
+    }
+}
+
+fn octile(a: IVec2, b: IVec2) -> u32 {
+    let d = (a - b).abs();
+    let (low, high) = (d.x.min(d.y) as u32, d.x.max(d.y) as u32);
+    DIAGONAL_COST * low + STRAIGHT_COST * (high - low)
+}
+
+fn rebuild_nav_grid(
+    added: Query<(), Added<Wall>>,
+    mut grid: ResMut<NavGrid>,
+    mut removed: RemovedComponents<Wall>,
+    walls: Query<&Transform, With<Wall>>,
+) {
+    // Read every removal, so none are left over to trigger another rebuild next frame.
+    let any_removed = removed.read().count() > 0;
+    if added.is_empty() && !any_removed {
+        return;
+    }
+    *grid = NavGrid::from_walls(walls.iter().map(|t| t.translation));
+}
+
+fn clear_flow_fields(mut cache: ResMut<FlowFieldCache>) {
+    cache.fields.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A vertical wall from y = -90 to y = 90 at x = 0.
+    fn wall_grid() -> NavGrid {
+        NavGrid::from_walls((-5..=5).map(|i| Vec3::new(0., i as f32 * CELL_SIZE, 0.)))
+    }
+
+    #[test]
+    fn path_routes_around_wall() {
+        let grid = wall_grid();
+        let from = Vec3::new(-60., 0., 0.);
+        let to = Vec3::new(60., 0., 0.);
+        assert!(!grid.line_of_sight(from, to));
+
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(path.last(), Some(&to));
+        assert!(path
+            .iter()
+            .any(|waypoint| waypoint.y.abs() > 5. * CELL_SIZE));
+        for waypoint in path.iter() {
+            assert!(grid.is_walkable(grid.cell_of(*waypoint)));
+        }
+    }
+
+    #[test]
+    fn flow_field_steers_away_from_wall() {
+        let grid = wall_grid();
+        let to = Vec3::new(60., 0., 0.);
+        let field = grid.flow_field(to);
+        let direction = grid.flow_direction(&field, Vec3::new(-60., 0., 0.), to);
+        assert!(direction.y.abs() > 0.5);
+    }
+
+    #[test]
+    fn open_space_goes_straight() {
+        let grid = wall_grid();
+        let mut agent = NavAgent::default();
+        let from = Vec3::new(-60., 0., 0.);
+        let to = Vec3::new(-60., 100., 0.);
+        assert_eq!(agent.direction(&grid, from, to, 0.), Vec3::Y);
+    }
+
+    #[test]
+    fn grid_rebuilds_for_walls_only() {
+        let mut app = App::new();
+        app.init_resource::<NavGrid>()
+            .add_systems(Update, rebuild_nav_grid);
+        let wall = app.world.spawn((Transform::default(), Wall)).id();
+        app.world
+            .spawn((Transform::from_xyz(CELL_SIZE * 4., 0., 0.), Wall));
+        app.update();
+        assert!(!app.world.resource::<NavGrid>().is_empty());
+
+        // Clear the grid out, so any rebuild shows. Hexlings coming and going don't cause one.
+        *app.world.resource_mut::<NavGrid>() = NavGrid::default();
+        let hexling = app
+            .world
+            .spawn((crate::hexling::Hexling, Transform::default()))
+            .id();
+        app.update();
+        app.world.despawn(hexling);
+        app.update();
+        assert!(app.world.resource::<NavGrid>().is_empty());
+
+        // A wall going away rebuilds it once, and only once.
+        app.world.despawn(wall);
+        app.update();
+        assert!(!app.world.resource::<NavGrid>().is_empty());
+        *app.world.resource_mut::<NavGrid>() = NavGrid::default();
+        app.update();
+        assert!(app.world.resource::<NavGrid>().is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+};
+use std::f32::consts::PI;
+
+use crate::{
+    combat::Health,
+    damage::{DamageKind, Resistances},
+    enemy::{spawn_octagon, Enemy},
+    food::spawn_food,
+    player::Player,
+    shapes::ShapeCache,
+    sound::SoundSettings,
+    swarm::spawn_swarm,
+    GameState,
+};
+
+pub const COLOR: Color = Color::rgb(0.45, 0.0, 0.15);
+// Each food pellet dropped when a nest is destroyed.
+const FOOD_DROPS: usize = 8;
+const MAX_BROOD: usize = 3;
+// Nests only breed while the player is within this distance.
+const RANGE: f32 = 350.;
+// Fleshy and damp: eaten away by acid, but it grounds out energy.
+const RESISTANCES: &[(DamageKind, f32)] =
+    &[(DamageKind::Energy, 0.5), (DamageKind::Corrosive, 1.5)];
+pub const RADIUS: f32 = 28.;
+const SPAWN_SECONDS: f32 = 6.;
+const STARTING_HEALTH: f32 = 40.;
+const SWARMLET_SIZE: usize = 60;
+
+// What a nest hatches, in rotation.
+#[derive(Debug, Clone, Copy)]
+pub enum Hatchling {
+    Octagon,
+    Swarm(usize),
+}
+
+#[derive(Component)]
+pub struct Nest {
+    // Living enemies hatched by this nest. A nest won't hatch more than `MAX_BROOD` at once.
+    brood: Vec<Entity>,
+    hatchlings: &'static [Hatchling],
+    next: usize,
+    spawn_timer: Timer,
+}
+
+#[derive(Event)]
+pub struct NestDestroyedEvent(pub Vec3);
+
+// Where the map generator decided nests should go. Nests are rebuilt here for each new game.
+#[derive(Default, Resource)]
+pub struct NestSites(pub Vec<Vec3>);
+
+pub struct NestPlugin;
+
+impl Plugin for NestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NestDestroyedEvent>()
+            .add_systems(Update, spawn_nests.run_if(resource_added::<NestSites>()))
+            .add_systems(OnExit(GameState::Over), spawn_nests)
+            .add_systems(
+                Update,
+                (
+                    hatch,
+                    nest_rewards
+                        .after(crate::damage::apply_damage)
+                        .before(crate::enemy::splodey),
+                    pulse_nests,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_nests(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut shapes: ResMut<ShapeCache>,
+    sites: Res<NestSites>,
+) {
+    for site in sites.0.iter() {
+        spawn_nest(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut shapes,
+            *site,
+        );
+    }
+}
+
+pub fn spawn_nest(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    translation: Vec3,
+) {
+    commands.spawn((
+        Enemy,
+        Health::new(STARTING_HEALTH),
+        MaterialMesh2dBundle {
+            mesh: shapes.mesh(meshes, 12, RADIUS),
+            material: shapes.material(materials, COLOR),
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        Name::new("nest"),
+        Nest {
+            brood: Vec::new(),
+            hatchlings: &[Hatchling::Octagon, Hatchling::Swarm(SWARMLET_SIZE)],
+            next: 0,
+            spawn_timer: Timer::from_seconds(SPAWN_SECONDS, TimerMode::Repeating),
+        },
+        Resistances(RESISTANCES),
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hatch(
+    mut commands: Commands,
+    enemy_query: Query<(), With<Enemy>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut nest_query: Query<(&mut Nest, &Transform)>,
+    player_query: Query<&Transform, With<Player>>,
+    mut shapes: ResMut<ShapeCache>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    for (mut nest, transform) in nest_query.iter_mut() {
+        nest.brood.retain(|entity| enemy_query.get(*entity).is_ok());
+        if (player_transform.translation - transform.translation).length() > RANGE {
+            continue;
+        }
+        if !nest.spawn_timer.tick(time.delta()).just_finished() || nest.brood.len() >= MAX_BROOD {
+            continue;
+        }
+
+        // Hatch just outside the nest, on the side facing the player.
+        let toward_player =
+            (player_transform.translation - transform.translation).normalize_or_zero();
+        let translation = transform.translation + toward_player * RADIUS * 2.;
+        let hatchling = match nest.hatchlings[nest.next] {
+            Hatchling::Octagon => spawn_octagon(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut shapes,
+                translation,
+            ),
+            Hatchling::Swarm(size) => spawn_swarm(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut shapes,
+                translation,
+                size,
+            ),
+        };
+        nest.brood.push(hatchling);
+        nest.next = (nest.next + 1) % nest.hatchlings.len();
+    }
+}
+
+// A nest breathes faster the closer it is to hatching.
+fn pulse_nests(mut nest_query: Query<(&Nest, &mut Transform)>, time: Res<Time>) {
+    for (nest, mut transform) in nest_query.iter_mut() {
+        let rate = 1. + 4. * nest.spawn_timer.percent();
+        let scale = 1. + 0.05 * (time.elapsed_seconds() * rate * PI).sin();
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+// The destruction itself (debris, despawn) is handled like any other enemy by `enemy::splodey`.
+#[allow(clippy::too_many_arguments)]
+fn nest_rewards(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    mut ev_nest_destroyed: EventWriter<NestDestroyedEvent>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    nest_query: Query<(&Health, &Transform), With<Nest>>,
+    mut shapes: ResMut<ShapeCache>,
+    sound_settings: Res<SoundSettings>,
+) {
+    for (health, transform) in nest_query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+
+        for i in 0..FOOD_DROPS {
+            let angle = i as f32 / FOOD_DROPS as f32 * 2. * PI;
+            let offset = Vec3::new(angle.cos(), angle.sin(), 0.) * RADIUS * 1.5;
+            spawn_food(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut shapes,
+                transform.translation + offset,
+            );
+        }
+        ev_nest_destroyed.send(NestDestroyedEvent(transform.translation));
+        commands.spawn((AudioBundle {
+            source: asset_server.load("audio/thud-thud.ogg"),
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Once,
+                volume: Volume::new_relative(sound_settings.effects_volume / 2.),
+                ..default()
+            },
+        },));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::ManualEventReader, system::RunSystemOnce};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{food::Food, swarm::Swarm};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<ShapeCache>()
+            .init_resource::<Time>()
+            .add_event::<NestDestroyedEvent>();
+        app.world.run_system_once(
+            |mut commands: Commands,
+             mut materials: ResMut<Assets<ColorMaterial>>,
+             mut meshes: ResMut<Assets<Mesh>>,
+             mut shapes: ResMut<ShapeCache>| {
+                spawn_nest(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &mut shapes,
+                    Vec3::ZERO,
+                );
+            },
+        );
+        app
+    }
+
+    fn nest(app: &mut App) -> Entity {
+        app.world
+            .query_filtered::<Entity, With<Nest>>()
+            .single(&app.world)
+    }
+
+    // Lets a full spawn period pass.
+    fn wait(app: &mut App) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(SPAWN_SECONDS));
+        app.update();
+    }
+
+    fn hatched(app: &mut App) -> Vec<Entity> {
+        app.world
+            .query_filtered::<Entity, (With<Enemy>, Without<Nest>)>()
+            .iter(&app.world)
+            .collect()
+    }
+
+    #[test]
+    fn hatches_only_with_the_player_in_range() {
+        let mut app = app();
+        app.add_systems(Update, hatch);
+        let player = app
+            .world
+            .spawn((
+                Player,
+                Transform::from_translation(Vec3::new(RANGE * 2., 0., 0.)),
+            ))
+            .id();
+        wait(&mut app);
+        assert!(hatched(&mut app).is_empty());
+
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::new(RANGE / 2., 0., 0.);
+        wait(&mut app);
+        assert_eq!(hatched(&mut app).len(), 1);
+    }
+
+    #[test]
+    fn brood_is_capped() {
+        let mut app = app();
+        app.add_systems(Update, hatch);
+        app.world.spawn((Player, Transform::default()));
+        for _ in 0..MAX_BROOD * 2 {
+            wait(&mut app);
+        }
+        let brood = hatched(&mut app);
+        assert_eq!(brood.len(), MAX_BROOD);
+
+        // Losing one makes room for another.
+        app.world.despawn(brood[0]);
+        wait(&mut app);
+        assert_eq!(hatched(&mut app).len(), MAX_BROOD);
+    }
+
+    #[test]
+    fn hatchlings_come_in_rotation() {
+        let mut app = app();
+        app.add_systems(Update, hatch);
+        app.world.spawn((Player, Transform::default()));
+        let mut swarms = Vec::new();
+        for _ in 0..MAX_BROOD {
+            wait(&mut app);
+            swarms.push(app.world.query::<&Swarm>().iter(&app.world).count());
+        }
+
+        // Octagon, swarm, octagon.
+        assert_eq!(swarms, vec![0, 1, 1]);
+        assert_eq!(hatched(&mut app).len(), 3);
+    }
+
+    #[test]
+    fn destroyed_nests_drop_food() {
+        let mut app = app();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .insert_resource(SoundSettings {
+                effects_on: true,
+                effects_volume: 0.5,
+                global_sound_on: true,
+                global_volume_db: 1.,
+                soundtrack_on: true,
+                soundtrack_volume: 1.,
+            })
+            .add_systems(Update, nest_rewards);
+        let nest = nest(&mut app);
+        app.update();
+        assert_eq!(app.world.query::<&Food>().iter(&app.world).count(), 0);
+
+        app.world.get_mut::<Health>(nest).unwrap().current = 0.;
+        app.update();
+        assert_eq!(
+            app.world.query::<&Food>().iter(&app.world).count(),
+            FOOD_DROPS
+        );
+        let events = app.world.resource::<Events<NestDestroyedEvent>>();
+        let destroyed: Vec<Vec3> = ManualEventReader::<NestDestroyedEvent>::default()
+            .read(events)
+            .map(|ev| ev.0)
+            .collect();
+        assert_eq!(destroyed, vec![Vec3::ZERO]);
+    }
+}
@@ -0,0 +1,478 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use std::collections::VecDeque;
+
+use crate::{
+    aim::AimPoint,
+    combat::Targeting,
+    enemy::Enemy,
+    groups::{ControlGroup, SelectedGroup},
+    hexling::Hexling,
+    navigation::{FlowFieldCache, NavGrid},
+    player::{
+        events::{ChargeEvent, RecallEvent},
+        Player,
+    },
+    shapes::ShapeCache,
+    steering::Boid,
+    GameState,
+};
+
+// A rally counts as reached within this distance, and the next queued order begins.
+const ARRIVE_DISTANCE: f32 = 30.;
+const BEACON_RADIUS: f32 = 7.;
+const GUARD_COLOR: Color = Color::rgb(0.3, 0.6, 2.);
+pub const GUARD_RADIUS: f32 = 120.;
+const PATROL_COLOR: Color = Color::rgb(1.8, 1.4, 0.2);
+const RALLY_COLOR: Color = Color::rgb(0.3, 2., 0.5);
+
+// Move to a point and hold there.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Rally {
+    pub point: Vec3,
+}
+
+// Walk a route of waypoints, round and round.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Patrol {
+    pub route: Vec<Vec3>,
+    pub next: usize,
+}
+
+// Hold a point, and engage anything that comes within the radius.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Guard {
+    pub point: Vec3,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Order {
+    Rally(Rally),
+    Patrol(Patrol),
+    Guard(Guard),
+}
+
+impl Order {
+    fn points(&self) -> Vec<Vec3> {
+        match self {
+            Order::Rally(rally) => vec![rally.point],
+            Order::Patrol(patrol) => patrol.route.clone(),
+            Order::Guard(guard) => vec![guard.point],
+        }
+    }
+}
+
+// Orders waiting for the current one to finish. Only a rally ever finishes; patrols and guards go on
+// until replaced.
+#[derive(Component, Debug, Default)]
+pub struct OrderQueue(pub VecDeque<Order>);
+
+// Hexlings following none of the above are left to charge and recall.
+pub type Unordered = (Without<Rally>, Without<Patrol>, Without<Guard>);
+pub type Ordered = Or<(With<Rally>, With<Patrol>, With<Guard>)>;
+
+#[derive(Event)]
+pub struct OrderEvent {
+    pub order: Order,
+    // Queue behind current orders rather than replacing them.
+    pub queued: bool,
+}
+
+// Marks an order's point on the map for as long as any hexling has business there.
+#[derive(Component)]
+pub struct Beacon {
+    point: Vec3,
+}
+
+pub struct OrdersPlugin;
+
+impl Plugin for OrdersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OrderEvent>()
+            .add_systems(
+                OnEnter(GameState::Over),
+                crate::menu::despawn_thing::<Beacon>,
+            )
+            .add_systems(
+                Update,
+                (
+                    order_controls,
+                    (issue_orders, place_beacons),
+                    cancel_orders,
+                    advance_orders,
+                    (rally, patrol, guard),
+                    tidy_beacons,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+// R rallies, G guards and P patrols, all at the aim point. Hold Ctrl to queue; queued patrol
+// waypoints add to the route rather than starting a new one.
+fn order_controls(
+    aim: Res<AimPoint>,
+    mut ev_order: EventWriter<OrderEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let Some(point) = aim.point else {
+        return;
+    };
+    let queued = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if keyboard_input.just_pressed(KeyCode::R) {
+        ev_order.send(OrderEvent {
+            order: Order::Rally(Rally { point }),
+            queued,
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::P) {
+        // A fresh patrol runs between here and the aim point.
+        let route = match player_query.get_single() {
+            Ok(player) if !queued => vec![player.translation, point],
+            _ => vec![point],
+        };
+        ev_order.send(OrderEvent {
+            order: Order::Patrol(Patrol { route, next: 0 }),
+            queued,
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::G) {
+        ev_order.send(OrderEvent {
+            order: Order::Guard(Guard {
+                point,
+                radius: GUARD_RADIUS,
+            }),
+            queued,
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn issue_orders(
+    mut commands: Commands,
+    mut ev_order: EventReader<OrderEvent>,
+    mut query: Query<
+        (
+            Entity,
+            &ControlGroup,
+            &mut OrderQueue,
+            Option<&Rally>,
+            Option<&Patrol>,
+            Option<&Guard>,
+        ),
+        With<Hexling>,
+    >,
+    selected: Res<SelectedGroup>,
+) {
+    let events: Vec<&OrderEvent> = ev_order.read().collect();
+    if events.is_empty() {
+        return;
+    }
+    for (entity, group, mut queue, rally, patrol, guard) in query.iter_mut() {
+        if !selected.contains(group) {
+            continue;
+        }
+        // Work on a copy, since several orders may arrive in the same frame.
+        let before = match (rally, patrol, guard) {
+            (Some(rally), _, _) => Some(Order::Rally(rally.clone())),
+            (_, Some(patrol), _) => Some(Order::Patrol(patrol.clone())),
+            (_, _, Some(guard)) => Some(Order::Guard(guard.clone())),
+            _ => None,
+        };
+        let mut current = before.clone();
+        for ev in events.iter() {
+            match current.as_mut() {
+                Some(current) if ev.queued => enqueue(current, &mut queue.0, &ev.order),
+                _ => {
+                    queue.0.clear();
+                    current = Some(ev.order.clone());
+                }
+            }
+        }
+        if let Some(order) = current.filter(|order| Some(order) != before.as_ref()) {
+            start(&mut commands, entity, order);
+        }
+    }
+}
+
+// A queued patrol waypoint extends the last patrol in line, if that's where it'd go.
+fn enqueue(current: &mut Order, queue: &mut VecDeque<Order>, order: &Order) {
+    if let Order::Patrol(Patrol { route, .. }) = order {
+        let last = match queue.back_mut() {
+            Some(last) => last,
+            None => current,
+        };
+        if let Order::Patrol(last) = last {
+            last.route.extend(route.iter().copied());
+            return;
+        }
+    }
+    queue.push_back(order.clone());
+}
+
+// Charge and recall take back control of the selected group.
+fn cancel_orders(
+    mut commands: Commands,
+    mut ev_charge: EventReader<ChargeEvent>,
+    mut ev_recall: EventReader<RecallEvent>,
+    mut query: Query<(Entity, &ControlGroup, &mut OrderQueue), With<Hexling>>,
+    selected: Res<SelectedGroup>,
+) {
+    if ev_charge.read().count() + ev_recall.read().count() == 0 {
+        return;
+    }
+    for (entity, group, mut queue) in query.iter_mut() {
+        if !selected.contains(group) {
+            continue;
+        }
+        queue.0.clear();
+        commands.entity(entity).remove::<(Rally, Patrol, Guard)>();
+    }
+}
+
+fn advance_orders(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut OrderQueue, &Rally, &Transform), With<Hexling>>,
+) {
+    for (entity, mut queue, rally, transform) in query.iter_mut() {
+        if (rally.point - transform.translation).length() > ARRIVE_DISTANCE {
+            continue;
+        }
+        if let Some(order) = queue.0.pop_front() {
+            start(&mut commands, entity, order);
+        }
+    }
+}
+
+fn start(commands: &mut Commands, entity: Entity, order: Order) {
+    let mut entity = commands.entity(entity);
+    entity.remove::<(Rally, Patrol, Guard)>();
+    match order {
+        Order::Rally(rally) => entity.insert(rally),
+        Order::Patrol(patrol) => entity.insert(patrol),
+        Order::Guard(guard) => entity.insert(guard),
+    };
+}
+
+fn rally(
+    mut flow_fields: ResMut<FlowFieldCache>,
+    nav_grid: Res<NavGrid>,
+    mut query: Query<(&mut Boid, &Rally, &mut Targeting, &Transform), With<Hexling>>,
+) {
+    for (mut boid, rally, mut targeting, transform) in query.iter_mut() {
+        targeting.target_list.clear();
+        let field = flow_fields.get_or_build(&nav_grid, rally.point);
+        let heading = nav_grid.flow_direction(field, transform.translation, rally.point);
+        boid.seek(heading, rally.point, 0.);
+    }
+}
+
+fn patrol(
+    mut flow_fields: ResMut<FlowFieldCache>,
+    nav_grid: Res<NavGrid>,
+    mut query: Query<(&mut Boid, &mut Patrol, &mut Targeting, &Transform), With<Hexling>>,
+) {
+    for (mut boid, mut patrol, mut targeting, transform) in query.iter_mut() {
+        targeting.target_list.clear();
+        if patrol.route.is_empty() {
+            continue;
+        }
+        if (patrol.route[patrol.next] - transform.translation).length() < ARRIVE_DISTANCE {
+            patrol.next = (patrol.next + 1) % patrol.route.len();
+        }
+        let waypoint = patrol.route[patrol.next];
+        let field = flow_fields.get_or_build(&nav_grid, waypoint);
+        let heading = nav_grid.flow_direction(field, transform.translation, waypoint);
+        // Don't dawdle at waypoints.
+        boid.wander(heading);
+        boid.goal = Some(waypoint);
+    }
+}
+
+fn guard(
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    mut flow_fields: ResMut<FlowFieldCache>,
+    nav_grid: Res<NavGrid>,
+    mut query: Query<(&mut Boid, &Guard, &mut Targeting, &Transform), With<Hexling>>,
+) {
+    for (mut boid, guard, mut targeting, transform) in query.iter_mut() {
+        // Engage whatever is closest to the guarded point, so long as it's inside the radius.
+        let intruder = enemy_query
+            .iter()
+            .map(|(entity, t)| {
+                (
+                    entity,
+                    t.translation,
+                    (t.translation - guard.point).length(),
+                )
+            })
+            .filter(|(_, _, distance)| *distance < guard.radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        targeting.target_list.clear();
+        let goal = match intruder {
+            Some((entity, translation, _)) => {
+                targeting.target_list.push(entity);
+                translation
+            }
+            None => guard.point,
+        };
+        let field = flow_fields.get_or_build(&nav_grid, goal);
+        let heading = nav_grid.flow_direction(field, transform.translation, goal);
+        boid.seek(heading, goal, 0.);
+    }
+}
+
+fn place_beacons(
+    mut commands: Commands,
+    mut ev_order: EventReader<OrderEvent>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    for ev in ev_order.read() {
+        let color = match ev.order {
+            Order::Rally(_) => RALLY_COLOR,
+            Order::Patrol(_) => PATROL_COLOR,
+            Order::Guard(_) => GUARD_COLOR,
+        };
+        for point in ev.order.points() {
+            commands.spawn((
+                Beacon { point },
+                MaterialMesh2dBundle {
+                    mesh: shapes.mesh(&mut meshes, 3, BEACON_RADIUS),
+                    material: shapes.material(&mut materials, color),
+                    transform: Transform::from_translation(point.truncate().extend(-0.5)),
+                    ..default()
+                },
+                Name::new("beacon"),
+            ));
+        }
+    }
+}
+
+fn tidy_beacons(
+    beacon_query: Query<(Entity, &Beacon)>,
+    mut commands: Commands,
+    guard_query: Query<&Guard>,
+    patrol_query: Query<&Patrol>,
+    queue_query: Query<&OrderQueue>,
+    rally_query: Query<&Rally>,
+) {
+    let mut points: Vec<Vec3> = Vec::new();
+    points.extend(rally_query.iter().map(|r| r.point));
+    points.extend(guard_query.iter().map(|g| g.point));
+    points.extend(patrol_query.iter().flat_map(|p| p.route.iter().copied()));
+    points.extend(
+        queue_query
+            .iter()
+            .flat_map(|q| q.0.iter().flat_map(|order| order.points())),
+    );
+    for (entity, beacon) in beacon_query.iter() {
+        if !points.contains(&beacon.point) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with_hexling(translation: Vec3) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<OrderEvent>()
+            .init_resource::<FlowFieldCache>()
+            .init_resource::<NavGrid>()
+            .init_resource::<SelectedGroup>()
+            .add_systems(
+                Update,
+                (issue_orders, advance_orders, (rally, patrol, guard)).chain(),
+            );
+        let hexling = app
+            .world
+            .spawn((
+                Boid::default(),
+                ControlGroup(1),
+                Hexling,
+                OrderQueue::default(),
+                Targeting::new(50.),
+                Transform::from_translation(translation),
+            ))
+            .id();
+        (app, hexling)
+    }
+
+    fn send(app: &mut App, order: Order, queued: bool) {
+        app.world.send_event(OrderEvent { order, queued });
+    }
+
+    #[test]
+    fn queued_orders_follow_a_rally() {
+        let (mut app, hexling) = app_with_hexling(Vec3::ZERO);
+        let point = Vec3::new(200., 0., 0.);
+        let guard = Guard {
+            point: Vec3::new(0., 200., 0.),
+            radius: GUARD_RADIUS,
+        };
+        send(&mut app, Order::Rally(Rally { point }), false);
+        send(&mut app, Order::Guard(guard.clone()), true);
+        app.update();
+        app.update();
+
+        let entity = app.world.entity(hexling);
+        assert_eq!(entity.get::<Rally>(), Some(&Rally { point }));
+        assert_eq!(entity.get::<OrderQueue>().unwrap().0.len(), 1);
+        assert_eq!(entity.get::<Boid>().unwrap().goal, Some(point));
+
+        // Arrive, and the guard order takes over.
+        app.world.get_mut::<Transform>(hexling).unwrap().translation = point;
+        app.update();
+        app.update();
+
+        let entity = app.world.entity(hexling);
+        assert!(entity.get::<Rally>().is_none());
+        assert_eq!(entity.get::<Guard>(), Some(&guard));
+        assert_eq!(entity.get::<Boid>().unwrap().goal, Some(guard.point));
+    }
+
+    #[test]
+    fn queued_waypoints_extend_a_patrol() {
+        let (mut app, hexling) = app_with_hexling(Vec3::ZERO);
+        for (point, queued) in [
+            (Vec3::new(100., 0., 0.), false),
+            (Vec3::new(100., 100., 0.), true),
+            (Vec3::new(0., 100., 0.), true),
+        ] {
+            send(
+                &mut app,
+                Order::Patrol(Patrol {
+                    route: vec![point],
+                    next: 0,
+                }),
+                queued,
+            );
+            app.update();
+        }
+
+        let entity = app.world.entity(hexling);
+        assert_eq!(entity.get::<Patrol>().unwrap().route.len(), 3);
+        assert!(entity.get::<OrderQueue>().unwrap().0.is_empty());
+
+        // An unqueued order replaces the patrol outright.
+        send(
+            &mut app,
+            Order::Rally(Rally {
+                point: Vec3::new(50., 50., 0.),
+            }),
+            false,
+        );
+        app.update();
+        let entity = app.world.entity(hexling);
+        assert!(entity.get::<Patrol>().is_none());
+        assert!(entity.get::<Rally>().is_some());
+    }
+}
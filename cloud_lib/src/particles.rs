@@ -0,0 +1,214 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, utils::HashMap};
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+use crate::{movement::Velocity, shapes::ShapeCache, GameState};
+
+// Fading pieces step through this many shades, so pieces of a colour can share materials.
+const FADE_STEPS: u8 = 8;
+const PRESETS: &str = include_str!("../data/particles.ron");
+
+#[derive(Debug, Deserialize)]
+pub struct EmitterPreset {
+    pub sides: usize,
+    pub radius: f32,
+    // Slowest and fastest a piece is thrown out.
+    pub speed: (f32, f32),
+    pub drag: f32,
+    pub spin: f32,
+    pub lifetime: f32,
+    pub fade: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    age: f32,
+    color: Color,
+    drag: f32,
+    fade: f32,
+    lifetime: f32,
+    spin: f32,
+    // How far through fading the piece's material is, out of `FADE_STEPS`.
+    step: u8,
+}
+
+// The presets, as read from `data/particles.ron`.
+#[derive(Resource)]
+pub struct Particles {
+    presets: HashMap<&'static str, EmitterPreset>,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self {
+            presets: ron::from_str(PRESETS).expect("particle presets should be valid"),
+        }
+    }
+}
+
+impl Particles {
+    // Throws `count` pieces of `color` out from `translation`, moving as the named preset says.
+    // Returns the pieces, for anything that wants to make more of them than scenery.
+    #[allow(clippy::too_many_arguments)]
+    pub fn burst(
+        &self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+        shapes: &mut ShapeCache,
+        preset: &str,
+        translation: Vec3,
+        color: Color,
+        count: usize,
+    ) -> Vec<Entity> {
+        let Some(preset) = self.presets.get(preset) else {
+            warn!("no particle preset called {}", preset);
+            return vec![];
+        };
+        let mesh = shapes.mesh(meshes, preset.sides, preset.radius);
+        let material = shapes.material(materials, color);
+
+        (0..count)
+            .map(|_| {
+                let angle = rand::random::<f32>() * 2. * PI;
+                let (slowest, fastest) = preset.speed;
+                let speed = slowest + rand::random::<f32>() * (fastest - slowest);
+                let shape = MaterialMesh2dBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(translation)
+                        .with_rotation(Quat::from_rotation_z(angle)),
+                    ..default()
+                };
+                // No `Collider`: pieces stay out of collision, and out of anything that reacts to
+                // what walks over it. Debris is collected by distance alone.
+                commands
+                    .spawn((
+                        shape,
+                        Velocity::new(Vec3::new(angle.cos(), angle.sin(), 0.) * speed),
+                        Particle {
+                            age: 0.,
+                            color,
+                            drag: preset.drag,
+                            fade: preset.fade,
+                            lifetime: preset.lifetime,
+                            spin: (rand::random::<f32>() * 2. - 1.) * preset.spin,
+                            step: 0,
+                        },
+                    ))
+                    .id()
+            })
+            .collect()
+    }
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Particles>().add_systems(
+            Update,
+            // The player's last burst carries on behind the game over screen.
+            update_particles
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Over))),
+        );
+    }
+}
+
+// Slows, spins, fades and finally clears away pieces. `movement` does the moving.
+fn update_particles(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(
+        Entity,
+        &mut Handle<ColorMaterial>,
+        &mut Particle,
+        &mut Transform,
+        &mut Velocity,
+    )>,
+    mut shapes: ResMut<ShapeCache>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut handle, mut particle, mut transform, mut velocity) in query.iter_mut() {
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let slowdown = (-particle.drag * delta).exp();
+        velocity.value *= slowdown;
+        particle.spin *= slowdown;
+        transform.rotate_z(particle.spin * delta);
+
+        let left = particle.lifetime - particle.age;
+        if left >= particle.fade {
+            continue;
+        }
+        let step = ((1. - left / particle.fade) * FADE_STEPS as f32) as u8;
+        if step != particle.step {
+            particle.step = step;
+            let mut color = particle.color;
+            color.set_a(color.a() * (1. - step as f32 / FADE_STEPS as f32));
+            *handle = shapes.material(&mut materials, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_there_and_sensible() {
+        let particles = Particles::default();
+        for name in ["enemy_death", "hexling_detonation", "player_death"] {
+            let preset = particles.presets.get(name).expect(name);
+            assert!(preset.speed.0 <= preset.speed.1, "{}", name);
+            assert!(preset.fade <= preset.lifetime, "{}", name);
+        }
+    }
+
+    #[test]
+    fn pieces_share_handles() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<Particles>()
+            .init_resource::<ShapeCache>();
+        app.add_systems(
+            Update,
+            |mut commands: Commands,
+             mut meshes: ResMut<Assets<Mesh>>,
+             mut materials: ResMut<Assets<ColorMaterial>>,
+             particles: Res<Particles>,
+             mut shapes: ResMut<ShapeCache>| {
+                for _ in 0..3 {
+                    particles.burst(
+                        &mut commands,
+                        &mut meshes,
+                        &mut materials,
+                        &mut shapes,
+                        "enemy_death",
+                        Vec3::ZERO,
+                        Color::RED,
+                        20,
+                    );
+                }
+            },
+        );
+        app.update();
+
+        assert_eq!(app.world.query::<&Particle>().iter(&app.world).count(), 60);
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<crate::collision::Collider>>()
+                .iter(&app.world)
+                .count(),
+            0
+        );
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 1);
+        assert_eq!(app.world.resource::<Assets<ColorMaterial>>().len(), 1);
+    }
+}
@@ -4,14 +4,35 @@ use bevy::prelude::*;
 use crate::sound::SoundSettings;
 use crate::GameState;
 
+// Which screen is showing while paused.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum PauseScreen {
+    #[default]
+    Menu,
+    Upgrades,
+}
+
 pub struct PauseMenuPlugin;
 
 impl Plugin for PauseMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Paused), init)
+        app.add_state::<PauseScreen>()
+            .add_systems(OnEnter(GameState::Paused), init)
             .add_systems(Update, pause_key.run_if(in_state(GameState::Playing)))
-            .add_systems(Update, menu.run_if(in_state(GameState::Paused)))
-            .add_systems(OnExit(GameState::Paused), despawn_screen::<PauseMenuScreen>);
+            .add_systems(
+                Update,
+                menu.run_if(in_state(GameState::Paused))
+                    .run_if(in_state(PauseScreen::Menu)),
+            )
+            .add_systems(OnEnter(PauseScreen::Upgrades), hide_screen)
+            .add_systems(
+                OnExit(PauseScreen::Upgrades),
+                show_screen.run_if(in_state(GameState::Paused)),
+            )
+            .add_systems(
+                OnExit(GameState::Paused),
+                (despawn_screen::<PauseMenuScreen>, close_screens),
+            );
     }
 }
 
@@ -64,7 +85,7 @@ fn init(
             ));
 
             builder.spawn(TextBundle::from_section(
-                "enter to play, esc to quit",
+                "enter to play, u for upgrades, esc to quit",
                 TextStyle {
                     font_size: 20.0,
                     color: Color::WHITE,
@@ -80,16 +101,40 @@ fn pause_key(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextSta
     }
 }
 
-fn menu(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+fn menu(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_screen: ResMut<NextState<PauseScreen>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
     if keyboard_input.just_pressed(KeyCode::Return) {
         next_state.set(GameState::Playing);
     }
 
+    if keyboard_input.just_pressed(KeyCode::U) {
+        next_screen.set(PauseScreen::Upgrades);
+    }
+
     if keyboard_input.just_pressed(KeyCode::Escape) {
         next_state.set(GameState::Menu);
     }
 }
 
+fn hide_screen(mut query: Query<&mut Visibility, With<PauseMenuScreen>>) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn show_screen(mut query: Query<&mut Visibility, With<PauseMenuScreen>>) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn close_screens(mut next_screen: ResMut<NextState<PauseScreen>>) {
+    next_screen.set(PauseScreen::Menu);
+}
+
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
         commands.entity(entity).despawn_recursive();
@@ -5,72 +5,176 @@ use bevy::{
     prelude::*,
     sprite::MaterialMesh2dBundle,
 };
+use std::collections::VecDeque;
 
+use crate::ability::{Abilities, DASH, PULSE};
+use crate::classes::{HexlingClass, SpawnClass};
 use crate::collision::Collider;
-use crate::enemy::CombatStats;
-use crate::movement::{MovingEntityBundle, Velocity};
+use crate::combat::Health;
+use crate::damage::{HitEvent, Interceptor, Warded};
+use crate::economy::Stockpile;
+use crate::groups::{ControlGroup, SelectedGroup};
+use crate::hexling::{Hexling, HexlingMode};
+use crate::movement::{
+    Locomotion, LocomotionProfile, LocomotionSettings, MovingEntityBundle, Velocity,
+};
+use crate::orders::Ordered;
+use crate::particles::Particles;
+use crate::shapes::ShapeCache;
 use crate::sound::SoundSettings;
+use crate::upgrades::Upgrades;
 use crate::GameState;
 
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
-pub enum HexlingState {
-    #[default]
-    Recalling,
-    Charging,
-}
-
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
-pub enum HexlingSpawnState {
-    #[default]
-    Idle,
-    Spawning,
-}
-
 pub const CHARGE_COLOR: Color = Color::rgb(3.25, 2.4, 1.1);
 const PLAYER_RADIUS: f32 = 30.;
 pub const RECALL_COLOR: Color = Color::rgb(0.25, 0.4, 0.1);
-const SPAWN_KEY_MS: u128 = 1500;
 pub const SPEED: f32 = 200.;
 const STARTING_HEALTH: f32 = 50.;
 pub const STARTING_TRANSLATION: Vec3 = Vec3::ZERO;
 
-#[derive(Default, Resource)]
-pub struct SpawnKeyHeld {
-    pub duration: u128,
+// Holding Left Shift builds up to a spawn, paid for in matter when it's queued.
+#[derive(Resource)]
+pub struct SpawnSettings {
+    // Queued hexlings hatch one at a time, this far apart.
+    pub hatch_seconds: f32,
+    // Each spawn takes this long holding the key. Holding on queues more.
+    pub hold_seconds: f32,
+    // Living and queued hexlings, before upgrades.
+    pub population: usize,
+}
+
+impl Default for SpawnSettings {
+    fn default() -> Self {
+        Self {
+            hatch_seconds: 0.3,
+            hold_seconds: 1.5,
+            population: 8,
+        }
+    }
+}
+
+impl SpawnSettings {
+    pub fn population_cap(&self, upgrades: &Upgrades) -> usize {
+        self.population + upgrades.population_bonus()
+    }
+}
+
+// Spawns paid for and waiting to hatch, and how far along the next one is.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnQueue {
+    pub held: f32,
+    pub queued: VecDeque<HexlingClass>,
+    hatch: f32,
+}
+
+impl SpawnQueue {
+    // Builds up the held time, queueing a `class` each time it fills while there's the matter and
+    // `room` for one. Otherwise it waits, full, until there is.
+    pub fn hold(
+        &mut self,
+        seconds: f32,
+        class: HexlingClass,
+        hold_seconds: f32,
+        room: usize,
+        stockpile: &mut Stockpile,
+    ) {
+        self.held += seconds;
+        while self.held >= hold_seconds {
+            let cost = class.stats().cost;
+            if self.queued.len() >= room || stockpile.matter < cost {
+                self.held = hold_seconds;
+                return;
+            }
+            stockpile.matter -= cost;
+            self.queued.push_back(class);
+            self.held -= hold_seconds;
+        }
+    }
+}
+
+// How the player weathers a hit and gets back on their feet.
+#[derive(Resource)]
+pub struct RecoverySettings {
+    // Blinks per second while invulnerable.
+    pub blink_rate: f32,
+    // Hexlings within this distance of the player count as docked.
+    pub dock_radius: f32,
+    // The most of any one hit that docked hexlings (and guardians) can take between them.
+    pub docked_max_share: f32,
+    // Health per second for each docked hexling. Unaffected by being hit.
+    pub docked_regen: f32,
+    // How much of a hit each recalled hexling in dock takes for the player.
+    pub docked_share: f32,
+    pub invulnerable_seconds: f32,
+    // Health per second, once the player has gone `regen_delay_seconds` without being hit.
+    pub passive_regen: f32,
+    pub regen_delay_seconds: f32,
+}
+
+impl Default for RecoverySettings {
+    fn default() -> Self {
+        Self {
+            blink_rate: 12.,
+            dock_radius: 100.,
+            docked_max_share: 0.6,
+            docked_regen: 0.1,
+            docked_share: 0.1,
+            invulnerable_seconds: 0.75,
+            passive_regen: 0.5,
+            regen_delay_seconds: 4.,
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct Player;
 
+#[derive(Component, Default)]
+pub struct Recovery {
+    // Seconds of invulnerability left.
+    invulnerable: f32,
+    since_hit: f32,
+}
+
+impl Recovery {
+    // Doesn't count as being hit, so regeneration carries on.
+    pub fn make_invulnerable(&mut self, seconds: f32) {
+        self.invulnerable = self.invulnerable.max(seconds);
+    }
+}
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Playing), spawn_player.run_if(run_once()))
             .add_systems(OnExit(GameState::Over), spawn_player)
-            .init_resource::<SpawnKeyHeld>()
+            .init_resource::<RecoverySettings>()
+            .init_resource::<SpawnQueue>()
+            .init_resource::<SpawnSettings>()
+            .add_systems(OnExit(GameState::Over), reset_spawn_queue)
             .add_systems(Update, player_controls.run_if(in_state(GameState::Playing)))
             .add_systems(
                 Update,
-                hexling_recall.run_if(in_state(HexlingState::Charging)),
+                (
+                    dock_guards.before(crate::damage::apply_damage),
+                    player_hit.after(crate::damage::apply_damage),
+                    player_recovery,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                hexling_charge.run_if(in_state(HexlingState::Recalling)),
+                (hexling_recall, hexling_charge).run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (
-                    hexling_spawn.run_if(in_state(HexlingSpawnState::Idle)),
-                    hexling_spawn_reset.run_if(in_state(HexlingSpawnState::Spawning)),
-                    splodey.run_if(in_state(GameState::Playing)),
-                ),
+                ((hexling_spawn, hatch).chain(), splodey).run_if(in_state(GameState::Playing)),
             )
             .add_event::<events::ChargeEvent>()
             .add_event::<events::RecallEvent>()
-            .add_event::<events::SpawnHexlingEvent>()
-            .add_state::<HexlingSpawnState>()
-            .add_state::<HexlingState>();
+            .add_event::<events::SpawnHexlingEvent>();
     }
 }
 
@@ -78,110 +182,228 @@ fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<RecoverySettings>,
+    mut shapes: ResMut<ShapeCache>,
 ) {
     let shape = MaterialMesh2dBundle {
-        mesh: meshes
-            .add(shape::RegularPolygon::new(PLAYER_RADIUS, 6).into())
-            .into(),
-        material: materials.add(ColorMaterial::from(RECALL_COLOR)),
+        mesh: shapes.mesh(&mut meshes, 6, PLAYER_RADIUS),
+        material: shapes.material(&mut materials, RECALL_COLOR),
         transform: Transform::from_translation(STARTING_TRANSLATION),
         ..default()
     };
 
     commands
         .spawn((
+            Abilities::new(&[&DASH, &PULSE]),
             AnimationPlayer::default(),
-            CombatStats {
-                aggro_radius: 0.,
-                attack_range: 0.,
-                attack_rate: 0.,
-                base_damage: 0.,
-                cooldown: 0.,
-                debris_despawn_timer: 0.,
-                health: STARTING_HEALTH,
-                target_list: Vec::new(),
-            },
+            Health::new(STARTING_HEALTH),
+            Locomotion::new(LocomotionProfile::Player),
             MovingEntityBundle {
                 collider: Collider::new(PLAYER_RADIUS),
                 shape,
                 velocity: Velocity::new(Vec3::ZERO),
             },
             Name::new("player"),
+            Recovery::default(),
+            Warded {
+                max_share: settings.docked_max_share,
+            },
         ))
         .insert(Player);
 }
 
 fn player_controls(
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Velocity, With<Player>>,
+    mut query: Query<&mut Locomotion, With<Player>>,
+    settings: Res<LocomotionSettings>,
 ) {
-    let Ok(mut velocity) = query.get_single_mut() else {
+    let Ok(mut locomotion) = query.get_single_mut() else {
         return;
     };
 
+    let mut input = Vec2::ZERO;
     if keyboard_input.pressed(KeyCode::W) {
-        velocity.value.y = SPEED;
-    } else if keyboard_input.pressed(KeyCode::S) {
-        velocity.value.y = -SPEED;
-    } else {
-        velocity.value.y = 0.;
+        input.y += 1.;
+    }
+    if keyboard_input.pressed(KeyCode::S) {
+        input.y -= 1.;
     }
-
     if keyboard_input.pressed(KeyCode::A) {
-        velocity.value.x = -SPEED;
-    } else if keyboard_input.pressed(KeyCode::D) {
-        velocity.value.x = SPEED;
-    } else {
-        velocity.value.x = 0.;
+        input.x -= 1.;
     }
+    if keyboard_input.pressed(KeyCode::D) {
+        input.x += 1.;
+    }
+    // Analog sticks may ask for less than full speed.
+    for gamepad in gamepads.iter() {
+        input.x += axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or(0.);
+        input.y += axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+            .unwrap_or(0.);
+    }
+
+    // No faster on the diagonal.
+    locomotion.intent =
+        input.clamp_length_max(1.).extend(0.) * settings.get(LocomotionProfile::Player).max_speed;
 }
 
-fn hexling_spawn(
-    mut ev_spawn_hexling: EventWriter<events::SpawnHexlingEvent>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut next_state: ResMut<NextState<HexlingSpawnState>>,
-    mut query: Query<Entity, With<Player>>,
-    mut spawn_key_held: ResMut<SpawnKeyHeld>,
-    time: Res<Time>,
+fn player_hit(
+    mut ev_hit: EventReader<HitEvent>,
+    mut query: Query<(Entity, &mut Health, &mut Recovery), With<Player>>,
+    settings: Res<RecoverySettings>,
 ) {
-    let Ok(entity) = query.get_single_mut() else {
+    let Ok((entity, mut health, mut recovery)) = query.get_single_mut() else {
         return;
     };
-    if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        spawn_key_held.duration += time.delta().as_millis();
-        if spawn_key_held.duration >= SPAWN_KEY_MS {
-            ev_spawn_hexling.send(events::SpawnHexlingEvent(entity));
-            next_state.set(HexlingSpawnState::Spawning);
+    // Read every hit, so none of them linger to re-arm invulnerability next frame.
+    if ev_hit.read().filter(|ev| ev.target == entity).count() > 0 {
+        recovery.invulnerable = settings.invulnerable_seconds;
+        recovery.since_hit = 0.;
+        health.invulnerable = true;
+    }
+}
+
+// Recalled hexlings close in around the player, and take a share of any hits meant for them.
+// Guardians always do, and keep their own, larger share.
+fn dock_guards(
+    mut commands: Commands,
+    ordered_query: Query<(), (With<Hexling>, Ordered)>,
+    query: Query<(Entity, &HexlingClass, &HexlingMode, Has<Interceptor>), With<Hexling>>,
+    settings: Res<RecoverySettings>,
+) {
+    for (entity, class, mode, intercepting) in query.iter() {
+        if class.stats().intercept_radius.is_some() {
+            continue;
+        }
+        let docked = *mode == HexlingMode::Recalling && !ordered_query.contains(entity);
+        if docked && !intercepting {
+            commands.entity(entity).insert(Interceptor {
+                radius: settings.dock_radius,
+                share: settings.docked_share,
+            });
+        } else if !docked && intercepting {
+            commands.entity(entity).remove::<Interceptor>();
         }
     }
 }
 
-fn hexling_spawn_reset(
+pub fn player_recovery(
+    hexling_query: Query<&Transform, (With<Hexling>, Without<Player>)>,
+    mut query: Query<(&mut Health, &mut Recovery, &Transform, &mut Visibility), With<Player>>,
+    settings: Res<RecoverySettings>,
+    time: Res<Time>,
+) {
+    let Ok((mut health, mut recovery, transform, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+
+    recovery.since_hit += time.delta_seconds();
+    recovery.invulnerable -= time.delta_seconds();
+    health.invulnerable = recovery.invulnerable > 0.;
+    *visibility =
+        if health.invulnerable && (recovery.invulnerable * settings.blink_rate).fract() < 0.5 {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+    let docked = hexling_query
+        .iter()
+        .filter(|t| (t.translation - transform.translation).length() < settings.dock_radius)
+        .count();
+    let passive = if recovery.since_hit >= settings.regen_delay_seconds {
+        settings.passive_regen
+    } else {
+        0.
+    };
+    health.regen = passive + docked as f32 * settings.docked_regen;
+}
+
+fn reset_spawn_queue(mut spawn_queue: ResMut<SpawnQueue>) {
+    *spawn_queue = SpawnQueue::default();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hexling_spawn(
+    hexling_query: Query<(), With<Hexling>>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut next_state: ResMut<NextState<HexlingSpawnState>>,
-    mut spawn_key_held: ResMut<SpawnKeyHeld>,
+    settings: Res<SpawnSettings>,
+    spawn_class: Res<SpawnClass>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut stockpile: ResMut<Stockpile>,
+    time: Res<Time>,
+    upgrades: Res<Upgrades>,
 ) {
-    if keyboard_input.just_released(KeyCode::ShiftLeft) {
-        spawn_key_held.duration = 0;
-        next_state.set(HexlingSpawnState::Idle);
+    if !keyboard_input.pressed(KeyCode::ShiftLeft) {
+        spawn_queue.held = 0.;
+        return;
+    }
+    let room = settings
+        .population_cap(&upgrades)
+        .saturating_sub(hexling_query.iter().count());
+    spawn_queue.hold(
+        time.delta_seconds(),
+        spawn_class.0,
+        settings.hold_seconds,
+        room,
+        &mut stockpile,
+    );
+}
+
+fn hatch(
+    mut ev_spawn_hexling: EventWriter<events::SpawnHexlingEvent>,
+    query: Query<Entity, With<Player>>,
+    settings: Res<SpawnSettings>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    time: Res<Time>,
+) {
+    let Ok(entity) = query.get_single() else {
+        return;
+    };
+    spawn_queue.hatch = (spawn_queue.hatch - time.delta_seconds()).max(0.);
+    if spawn_queue.hatch > 0. {
+        return;
+    }
+    if let Some(class) = spawn_queue.queued.pop_front() {
+        ev_spawn_hexling.send(events::SpawnHexlingEvent(entity, class));
+        spawn_queue.hatch = settings.hatch_seconds;
     }
 }
 
+// Space recalls the selected group once all of it is charging, and charges it otherwise.
+fn selection_charging(
+    hexling_query: &Query<(&ControlGroup, &HexlingMode), With<Hexling>>,
+    selected: &SelectedGroup,
+) -> bool {
+    let mut modes = hexling_query
+        .iter()
+        .filter(|(group, _)| selected.contains(group))
+        .map(|(_, mode)| *mode)
+        .peekable();
+    modes.peek().is_some() && modes.all(|mode| mode == HexlingMode::Charging)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn hexling_recall(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
     mut ev_recall: EventWriter<events::RecallEvent>,
+    hexling_query: Query<(&ControlGroup, &HexlingMode), With<Hexling>>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut next_state: ResMut<NextState<HexlingState>>,
     mut query: Query<Entity, With<Player>>,
+    selected: Res<SelectedGroup>,
     sound_settings: Res<crate::sound::SoundSettings>,
 ) {
     let Ok(entity) = query.get_single_mut() else {
         return;
     };
-    if keyboard_input.just_released(KeyCode::Space) {
+    if keyboard_input.just_released(KeyCode::Space) && selection_charging(&hexling_query, &selected)
+    {
         ev_recall.send(events::RecallEvent(entity));
-        next_state.set(HexlingState::Recalling);
         commands.spawn((AudioBundle {
             source: asset_server.load("audio/a.ogg"),
             settings: PlaybackSettings {
@@ -193,19 +415,23 @@ fn hexling_recall(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn hexling_charge(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
     mut ev_charge: EventWriter<events::ChargeEvent>,
+    hexling_query: Query<(&ControlGroup, &HexlingMode), With<Hexling>>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut next_state: ResMut<NextState<HexlingState>>,
     mut query: Query<Entity, With<Player>>,
+    selected: Res<SelectedGroup>,
     sound_settings: Res<SoundSettings>,
 ) {
     let Ok(entity) = query.get_single_mut() else {
         return;
     };
-    if keyboard_input.just_released(KeyCode::Space) {
+    if keyboard_input.just_released(KeyCode::Space)
+        && !selection_charging(&hexling_query, &selected)
+    {
         let settings = PlaybackSettings {
             mode: PlaybackMode::Once,
             volume: Volume::new_relative(sound_settings.effects_volume),
@@ -214,7 +440,6 @@ fn hexling_charge(
         // Is this smart? Probably not, but it makes a neat effect, and is slightly different with
         // its timings each time!
         ev_charge.send(events::ChargeEvent(entity));
-        next_state.set(HexlingState::Charging);
         commands.spawn((AudioBundle {
             source: asset_server.load("audio/e.ogg"),
             settings,
@@ -242,41 +467,62 @@ fn hexling_charge(
     }
 }
 
-// Another opportunity for a generic, but we'll go simple for now.
 fn splodey(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut next_state: ResMut<NextState<GameState>>,
-    query: Query<(Entity, &CombatStats, &Transform), With<Player>>,
+    particles: Res<Particles>,
+    query: Query<(Entity, &Health, &Transform), With<Player>>,
+    mut shapes: ResMut<ShapeCache>,
 ) {
-    let Ok((entity, stats, transform)) = query.get_single() else {
+    let Ok((entity, health, transform)) = query.get_single() else {
         return;
     };
 
-    if stats.health <= 0. {
-        for _ in 0..500 {
-            let shape = MaterialMesh2dBundle {
-                mesh: meshes.add(shape::RegularPolygon::new(6., 6).into()).into(),
-                material: materials.add(ColorMaterial::from(CHARGE_COLOR)),
-                transform: Transform::from_translation(transform.translation).with_rotation(
-                    Quat::from_rotation_z(rand::random::<f32>() * 2. * std::f32::consts::PI),
-                ),
-                ..default()
-            };
-
-            commands
-                .spawn(MovingEntityBundle {
-                    collider: Collider::new(6.),
-                    shape,
-                    velocity: Velocity::new(Vec3::ZERO),
-                })
-                .insert(crate::enemy::Debris {
-                    despawn_timer: 100.,
-                });
-        }
+    if health.is_dead() {
+        crate::enemy::spawn_debris(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut shapes,
+            &particles,
+            "player_death",
+            transform.translation,
+            CHARGE_COLOR,
+            500,
+            0.,
+        );
 
         commands.entity(entity).despawn_recursive();
         next_state.set(GameState::Over);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holding_queues_what_can_be_paid_for() {
+        let mut queue = SpawnQueue::default();
+        let cost = HexlingClass::Striker.stats().cost;
+        let mut stockpile = Stockpile { matter: cost * 2.5 };
+
+        queue.hold(1., HexlingClass::Striker, 1.5, 10, &mut stockpile);
+        assert!(queue.queued.is_empty());
+        queue.hold(2.5, HexlingClass::Striker, 1.5, 10, &mut stockpile);
+        assert_eq!(queue.queued.len(), 2);
+        assert_eq!(stockpile.matter, cost * 0.5);
+
+        // Out of matter: the spawn waits, ready to go as soon as there's enough.
+        queue.hold(5., HexlingClass::Striker, 1.5, 10, &mut stockpile);
+        assert_eq!(queue.queued.len(), 2);
+        assert_eq!(queue.held, 1.5);
+
+        // And the population cap counts what's already queued.
+        stockpile.matter = 100.;
+        queue.hold(1.5, HexlingClass::Striker, 1.5, 2, &mut stockpile);
+        assert_eq!(queue.queued.len(), 2);
+    }
+}
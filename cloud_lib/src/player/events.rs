@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::classes::HexlingClass;
+
 // Charge: player is sending hexlings away from themselves.
 #[derive(Event)]
 pub struct ChargeEvent(pub Entity);
@@ -8,6 +10,6 @@ pub struct ChargeEvent(pub Entity);
 #[derive(Event)]
 pub struct RecallEvent(pub Entity);
 
-// SpawnHexling: does what it says on the tin.
+// SpawnHexling: does what it says on the tin, with the class to spawn.
 #[derive(Event)]
-pub struct SpawnHexlingEvent(pub Entity);
+pub struct SpawnHexlingEvent(pub Entity, pub HexlingClass);
@@ -0,0 +1,152 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+};
+
+use crate::{
+    combat::{Attacker, Health},
+    damage::DamageEvent,
+    enemy::Enemy,
+    groups::{ControlGroup, SelectedGroup},
+    hexling::Hexling,
+    particles::Particles,
+    shapes::ShapeCache,
+    sound::SoundSettings,
+    GameState,
+};
+
+// A detonating hexling deals this much damage per point of health it had left...
+const DAMAGE_PER_HEALTH: f32 = 1.5;
+// ...and throws out this many shards of itself per point.
+const DEBRIS_PER_HEALTH: f32 = 2.;
+const MAX_DEBRIS: usize = 40;
+// The blast reaches this far, plus a little more for each point of health.
+const RADIUS: f32 = 40.;
+const RADIUS_PER_HEALTH: f32 = 3.;
+
+// How hard and how far a hexling with `health` left blows up.
+fn blast(health: f32) -> (f32, f32) {
+    let health = health.max(0.);
+    (
+        health * DAMAGE_PER_HEALTH,
+        RADIUS + health * RADIUS_PER_HEALTH,
+    )
+}
+
+pub struct SacrificePlugin;
+
+impl Plugin for SacrificePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            detonate
+                .before(crate::damage::apply_damage)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+// X blows up every hexling in the selected group, trading the swarm for one big hit. They're
+// cleared away with the rest of the dead by `hexling::hexling_deaths`.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn detonate(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    mut ev_damage: EventWriter<DamageEvent>,
+    mut hexling_query: Query<
+        (
+            Entity,
+            &Attacker,
+            &ControlGroup,
+            &Handle<ColorMaterial>,
+            &mut Health,
+            &Transform,
+        ),
+        With<Hexling>,
+    >,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    particles: Res<Particles>,
+    selected: Res<SelectedGroup>,
+    mut shapes: ResMut<ShapeCache>,
+    sound_settings: Res<SoundSettings>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::X) {
+        return;
+    }
+
+    let mut detonated = 0;
+    for (entity, attacker, group, handle, mut health, transform) in hexling_query.iter_mut() {
+        if !selected.contains(group) || health.is_dead() {
+            continue;
+        }
+        let (damage, radius) = blast(health.current);
+        for (enemy, enemy_transform) in enemy_query.iter() {
+            if (enemy_transform.translation - transform.translation).length() < radius {
+                ev_damage.send(DamageEvent {
+                    target: enemy,
+                    source: Some(entity),
+                    amount: damage,
+                    kind: attacker.damage_kind,
+                });
+            }
+        }
+
+        let color = materials.get(handle).map_or(Color::GREEN, |m| m.color);
+        let count = ((health.current * DEBRIS_PER_HEALTH) as usize).min(MAX_DEBRIS);
+        crate::enemy::spawn_debris(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut shapes,
+            &particles,
+            "hexling_detonation",
+            transform.translation,
+            color,
+            count,
+            // Nothing to salvage: a hexling spent is gone for good.
+            0.,
+        );
+        health.current = 0.;
+        detonated += 1;
+    }
+    if detonated == 0 {
+        return;
+    }
+
+    // One chord however many go up at once; a bigger sacrifice is just louder.
+    let settings = PlaybackSettings {
+        mode: PlaybackMode::Once,
+        volume: Volume::new_relative(
+            sound_settings.effects_volume * (1. + detonated as f32 * 0.1).min(2.),
+        ),
+        ..default()
+    };
+    for note in [
+        "audio/d.ogg",
+        "audio/fsharp3.ogg",
+        "audio/a.ogg",
+        "audio/thud.ogg",
+    ] {
+        commands.spawn((AudioBundle {
+            source: asset_server.load(note),
+            settings,
+        },));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healthier_hexlings_blow_up_bigger() {
+        let (weak_damage, weak_radius) = blast(1.);
+        let (strong_damage, strong_radius) = blast(10.);
+        assert!(strong_damage > weak_damage);
+        assert!(strong_radius > weak_radius);
+        assert_eq!(blast(-1.), (0., RADIUS));
+    }
+}
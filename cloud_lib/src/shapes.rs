@@ -0,0 +1,195 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle, utils::HashMap};
+
+// Colours are rounded to this many steps per unit of each channel, so that near enough colours
+// share a material rather than each random shade getting its own.
+const COLOR_STEPS: f32 = 32.;
+
+// Shared handles for the regular polygons everything is drawn with. Entities drawn with the same
+// handles batch together, and spawning another of something doesn't add another asset.
+//
+// Cached materials are shared: to recolour one entity, give it another handle from here rather than
+// changing the material under it.
+#[derive(Resource, Default)]
+pub struct ShapeCache {
+    materials: HashMap<[i32; 4], Handle<ColorMaterial>>,
+    meshes: HashMap<(usize, u32), Mesh2dHandle>,
+}
+
+impl ShapeCache {
+    pub fn mesh(&mut self, meshes: &mut Assets<Mesh>, sides: usize, radius: f32) -> Mesh2dHandle {
+        self.meshes
+            .entry((sides, radius.to_bits()))
+            .or_insert_with(|| {
+                meshes
+                    .add(shape::RegularPolygon::new(radius, sides).into())
+                    .into()
+            })
+            .clone()
+    }
+
+    pub fn material(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        let key = color
+            .as_rgba_f32()
+            .map(|channel| (channel * COLOR_STEPS).round() as i32);
+        self.materials
+            .entry(key)
+            .or_insert_with(|| {
+                let [r, g, b, a] = key.map(|step| step as f32 / COLOR_STEPS);
+                materials.add(ColorMaterial::from(Color::rgba(r, g, b, a)))
+            })
+            .clone()
+    }
+}
+
+pub struct ShapePlugin;
+
+impl Plugin for ShapePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShapeCache>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::audio::AudioSource;
+    use bevy_rand::prelude::*;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        classes::HexlingClass,
+        combat::Health,
+        enemy::{spawn_octagon, splodey, Enemy},
+        fog::{FogMaterial, HexlingFogTracker},
+        hexling::{hexling_spawner, Hexling},
+        map::{generate_level_map, Source},
+        particles::Particles,
+        player::{events::SpawnHexlingEvent, Player},
+        sound::SoundSettings,
+    };
+
+    const FRAMES: usize = 20;
+    // Walls come in random shades, quantized to a handful of materials. A few frames' worth of rooms
+    // turns up nearly all of them; after that, a rare one may still turn up for the first time.
+    const SPARE_SHADES: usize = 2;
+    const WARM_UP: usize = 3;
+
+    // Hexlings come in random shades, so there's just the one, hatched up front and kept throughout.
+    fn setup(mut commands: Commands, mut ev_spawn_hexling: EventWriter<SpawnHexlingEvent>) {
+        commands.spawn((EntropyComponent::<ChaCha8Rng>::from_seed([1; 32]), Source));
+        let player = commands.spawn((Player, Transform::default())).id();
+        ev_spawn_hexling.send(SpawnHexlingEvent(player, HexlingClass::Striker));
+    }
+
+    // Clears away everything drawn but the hexling, leaving it, the fog, player and random source for
+    // the next round.
+    fn despawn_all(
+        mut commands: Commands,
+        query: Query<Entity, (With<Handle<ColorMaterial>>, Without<Hexling>)>,
+    ) {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    fn spawn_wave(
+        mut commands: Commands,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut shapes: ResMut<ShapeCache>,
+    ) {
+        for i in 0..10 {
+            spawn_octagon(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut shapes,
+                Vec3::new(i as f32 * 10., 0., 0.),
+            );
+        }
+    }
+
+    fn kill_enemies(mut query: Query<&mut Health, With<Enemy>>) {
+        for mut health in query.iter_mut() {
+            health.current = 0.;
+        }
+    }
+
+    fn counts(app: &App) -> (usize, usize) {
+        (
+            app.world.resource::<Assets<Mesh>>().len(),
+            app.world.resource::<Assets<ColorMaterial>>().len(),
+        )
+    }
+
+    #[test]
+    fn asset_counts_stay_bounded() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .init_asset::<ColorMaterial>()
+            .init_asset::<FogMaterial>()
+            .init_asset::<Mesh>()
+            .add_event::<SpawnHexlingEvent>()
+            .init_resource::<HexlingFogTracker>()
+            .init_resource::<Particles>()
+            .init_resource::<ShapeCache>()
+            .insert_resource(SoundSettings {
+                effects_on: true,
+                effects_volume: 0.5,
+                global_sound_on: true,
+                global_volume_db: 1.,
+                soundtrack_on: true,
+                soundtrack_volume: 1.,
+            })
+            .add_systems(Startup, (setup, crate::fog::init))
+            .add_systems(
+                Update,
+                (
+                    despawn_all,
+                    generate_level_map,
+                    spawn_wave,
+                    hexling_spawner,
+                    apply_deferred,
+                    kill_enemies,
+                    splodey,
+                )
+                    .chain(),
+            );
+
+        // A room and a wave of enemies blown to bits, every frame.
+        for _ in 0..WARM_UP {
+            app.update();
+        }
+        let (meshes, materials) = counts(&app);
+        assert!(app
+            .world
+            .query::<&Enemy>()
+            .iter(&app.world)
+            .next()
+            .is_none());
+        let drawn = app
+            .world
+            .query::<&Handle<ColorMaterial>>()
+            .iter(&app.world)
+            .count();
+        assert!(drawn > materials);
+
+        for _ in 0..FRAMES {
+            app.update();
+        }
+        let (meshes_after, materials_after) = counts(&app);
+        assert_eq!(meshes_after, meshes);
+        assert!(
+            materials_after <= materials + SPARE_SHADES,
+            "{} materials went up to {} over {} frames",
+            materials,
+            materials_after,
+            FRAMES
+        );
+    }
+}
@@ -81,7 +81,7 @@ fn sound_controls(
     if keyboard_input.just_pressed(KeyCode::Plus) && sound_settings.global_volume_db < MAX_VOLUME_DB
     {
         // Need to do two things: first, change the global level for all future sounds.
-        sound_settings.global_volume_db = sound_settings.global_volume_db + VOLUME_STEP_DB;
+        sound_settings.global_volume_db += VOLUME_STEP_DB;
         let amplitude = global_volume.volume.get() * 10_f32.powf(VOLUME_STEP_DB / 20.0);
         global_volume.volume = VolumeLevel::new(amplitude);
 
@@ -97,7 +97,7 @@ fn sound_controls(
     if keyboard_input.just_pressed(KeyCode::Minus)
         && sound_settings.global_volume_db > MIN_VOLUME_DB
     {
-        sound_settings.global_volume_db = sound_settings.global_volume_db - VOLUME_STEP_DB;
+        sound_settings.global_volume_db -= VOLUME_STEP_DB;
         let amplitude = global_volume.volume.get() * 10_f32.powf(-VOLUME_STEP_DB / 20.0);
         global_volume.volume = VolumeLevel::new(amplitude);
 
@@ -0,0 +1,412 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, utils::HashMap};
+
+use crate::{
+    collision::Collider,
+    damage::{DamageEvent, DamageKind},
+    feedback::Flash,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    movement::Velocity,
+    shapes::ShapeCache,
+    GameState,
+};
+
+// How strongly a status colour is blended over an entity's own colour.
+const TINT_STRENGTH: f32 = 0.6;
+// Attacks slow down no further than this, even under the heaviest slow.
+const MIN_ATTACK_SPEED: f32 = 0.1;
+const MODIFIER_SOURCE: &str = "status";
+pub const TRAP_COLOR: Color = Color::rgb(0.25, 0.05, 0.3);
+const TRAP_RADIUS: f32 = 30.;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum StatusKind {
+    Slow,
+    Stun,
+    Poison,
+    Burn,
+    Shield,
+}
+
+impl StatusKind {
+    // What kind of damage an effect deals over time, if any.
+    pub fn damage_kind(&self) -> Option<DamageKind> {
+        match self {
+            StatusKind::Poison => Some(DamageKind::Corrosive),
+            StatusKind::Burn => Some(DamageKind::Thermal),
+            _ => None,
+        }
+    }
+
+    pub fn tint(&self) -> Color {
+        match self {
+            StatusKind::Slow => Color::rgb(0.2, 0.4, 1.),
+            StatusKind::Stun => Color::rgb(1., 1., 0.3),
+            StatusKind::Poison => Color::rgb(0.5, 1., 0.),
+            StatusKind::Burn => Color::rgb(1.5, 0.4, 0.),
+            StatusKind::Shield => Color::rgb(0.6, 0.9, 1.2),
+        }
+    }
+}
+
+// A timed effect. What `magnitude` means depends on the kind:
+//   - Slow: fraction of speed (and attack recovery) lost per stack.
+//   - Poison, Burn: damage per second per stack.
+//   - Shield: damage absorbed per stack.
+//   - Stun: unused. Stunned things neither move nor attack.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: f32,
+    pub magnitude: f32,
+    pub max_stacks: u32,
+}
+
+impl StatusEffect {
+    pub fn slow(duration: f32, fraction: f32) -> Self {
+        Self {
+            kind: StatusKind::Slow,
+            duration,
+            magnitude: fraction,
+            max_stacks: 3,
+        }
+    }
+
+    pub fn stun(duration: f32) -> Self {
+        Self {
+            kind: StatusKind::Stun,
+            duration,
+            magnitude: 0.,
+            max_stacks: 1,
+        }
+    }
+
+    pub fn poison(duration: f32, damage_per_second: f32) -> Self {
+        Self {
+            kind: StatusKind::Poison,
+            duration,
+            magnitude: damage_per_second,
+            max_stacks: 5,
+        }
+    }
+
+    pub fn burn(duration: f32, damage_per_second: f32) -> Self {
+        Self {
+            kind: StatusKind::Burn,
+            duration,
+            magnitude: damage_per_second,
+            max_stacks: 3,
+        }
+    }
+
+    pub fn shield(duration: f32, absorb: f32) -> Self {
+        Self {
+            kind: StatusKind::Shield,
+            duration,
+            magnitude: absorb,
+            max_stacks: 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActiveEffect {
+    effect: StatusEffect,
+    remaining: f32,
+    // Shields are used up as they absorb damage.
+    shield_left: f32,
+    stacks: u32,
+}
+
+#[derive(Component, Debug, Default)]
+pub struct StatusEffects {
+    active: Vec<ActiveEffect>,
+    // The entity's own material, remembered while a tint is applied over it.
+    base_material: Option<Handle<ColorMaterial>>,
+}
+
+impl StatusEffects {
+    // Re-applying an effect adds a stack (up to its maximum) and refreshes the duration.
+    pub fn apply(&mut self, effect: StatusEffect) {
+        match self
+            .active
+            .iter_mut()
+            .find(|a| a.effect.kind == effect.kind)
+        {
+            Some(active) => {
+                if active.stacks < effect.max_stacks {
+                    active.stacks += 1;
+                    active.shield_left += effect.magnitude;
+                }
+                active.remaining = active.remaining.max(effect.duration);
+                active.effect.magnitude = effect.magnitude;
+            }
+            None => self.active.push(ActiveEffect {
+                effect,
+                remaining: effect.duration,
+                shield_left: effect.magnitude,
+                stacks: 1,
+            }),
+        }
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.has(StatusKind::Stun)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|a| a.effect.kind == kind)
+    }
+
+    // How much slows and stuns hold an entity back, from 1 (not at all) down to 0.
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.is_stunned() {
+            return 0.;
+        }
+        self.active
+            .iter()
+            .filter(|a| a.effect.kind == StatusKind::Slow)
+            .map(|a| (1. - a.effect.magnitude).max(0.).powi(a.stacks as i32))
+            .product()
+    }
+
+    // Soaks up as much of `damage` as any shield allows, returning what gets through.
+    pub fn absorb(&mut self, damage: f32) -> f32 {
+        let mut damage = damage;
+        for active in self
+            .active
+            .iter_mut()
+            .filter(|a| a.effect.kind == StatusKind::Shield)
+        {
+            let absorbed = damage.min(active.shield_left);
+            active.shield_left -= absorbed;
+            damage -= absorbed;
+        }
+        self.active
+            .retain(|a| a.effect.kind != StatusKind::Shield || a.shield_left > 0.);
+        damage
+    }
+
+    // The most recently applied effect wins the colour.
+    pub fn tint(&self) -> Option<Color> {
+        self.active.last().map(|a| a.effect.kind.tint())
+    }
+
+    // The entity's own material while a tint covers it, for anything that wants to change it.
+    pub fn base_material_mut(&mut self) -> Option<&mut Handle<ColorMaterial>> {
+        self.base_material.as_mut()
+    }
+}
+
+#[derive(Event)]
+pub struct ApplyStatusEvent {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+// Applies its effect to anything that moves through it.
+#[derive(Component)]
+pub struct Trap {
+    pub effect: StatusEffect,
+    pub radius: f32,
+}
+
+// Walks through traps without springing them.
+#[derive(Component)]
+pub struct TrapSense;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatusEvent>().add_systems(
+            Update,
+            (
+                spring_traps,
+                apply_status,
+                tick_status,
+                status_modifiers,
+                tint_status,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+// A tar pit: slows whatever wades through it.
+pub fn spawn_trap(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    translation: Vec3,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: shapes.mesh(meshes, 5, TRAP_RADIUS),
+            material: shapes.material(materials, TRAP_COLOR),
+            // Sit beneath everything else.
+            transform: Transform::from_translation(translation.truncate().extend(-1.)),
+            ..default()
+        },
+        Name::new("trap"),
+        Trap {
+            effect: StatusEffect {
+                max_stacks: 1,
+                ..StatusEffect::slow(1., 0.5)
+            },
+            radius: TRAP_RADIUS,
+        },
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+fn spring_traps(
+    mut ev_apply_status: EventWriter<ApplyStatusEvent>,
+    query: Query<(Entity, &Transform), (With<Collider>, With<Velocity>, Without<TrapSense>)>,
+    trap_query: Query<(&Trap, &Transform)>,
+) {
+    for (trap, trap_transform) in trap_query.iter() {
+        for (entity, transform) in query.iter() {
+            let distance = (transform.translation - trap_transform.translation).truncate();
+            if distance.length() < trap.radius {
+                ev_apply_status.send(ApplyStatusEvent {
+                    target: entity,
+                    effect: trap.effect,
+                });
+            }
+        }
+    }
+}
+
+fn apply_status(
+    mut commands: Commands,
+    mut ev_apply_status: EventReader<ApplyStatusEvent>,
+    mut query: Query<Option<&mut StatusEffects>>,
+) {
+    // Entities affected for the first time this frame; gathered so several effects landing at once
+    // don't overwrite each other on insert.
+    let mut fresh: HashMap<Entity, StatusEffects> = HashMap::new();
+    for ev in ev_apply_status.read() {
+        let Ok(effects) = query.get_mut(ev.target) else {
+            continue;
+        };
+        match effects {
+            Some(mut effects) => effects.apply(ev.effect),
+            None => fresh.entry(ev.target).or_default().apply(ev.effect),
+        }
+    }
+    for (entity, effects) in fresh {
+        commands.entity(entity).insert(effects);
+    }
+}
+
+fn tick_status(
+    mut ev_damage: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut effects) in query.iter_mut() {
+        for active in effects.active.iter() {
+            if let Some(kind) = active.effect.kind.damage_kind() {
+                ev_damage.send(DamageEvent {
+                    target: entity,
+                    source: None,
+                    amount: active.effect.magnitude * active.stacks as f32 * delta,
+                    kind,
+                });
+            }
+        }
+
+        for active in effects.active.iter_mut() {
+            active.remaining -= delta;
+        }
+        effects.active.retain(|a| a.remaining > 0.);
+    }
+}
+
+// Slows hold back movement and stretch out the time between attacks.
+fn status_modifiers(mut query: Query<(&StatusEffects, &mut StatModifiers)>) {
+    for (effects, mut modifiers) in query.iter_mut() {
+        let speed = effects.speed_multiplier();
+        if speed >= 1. {
+            modifiers.remove(MODIFIER_SOURCE);
+            continue;
+        }
+        modifiers.add(StatModifier::new(
+            Stat::MoveSpeed,
+            ModifierOp::Mul(speed),
+            MODIFIER_SOURCE,
+        ));
+        modifiers.add(StatModifier::new(
+            Stat::AttackRate,
+            ModifierOp::Mul(1. / speed.max(MIN_ATTACK_SPEED)),
+            MODIFIER_SOURCE,
+        ));
+    }
+}
+
+fn tint_status(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    // A flash owns the colour while it lasts.
+    mut query: Query<(&mut StatusEffects, &mut Handle<ColorMaterial>), Without<Flash>>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    for (mut effects, mut handle) in query.iter_mut() {
+        let tinted = match effects.tint() {
+            Some(tint) => {
+                let base = effects.base_material.get_or_insert_with(|| handle.clone());
+                let Some(base) = materials.get(&*base).map(|m| m.color) else {
+                    continue;
+                };
+                shapes.material(&mut materials, mix(base, tint, TINT_STRENGTH))
+            }
+            None => match effects.base_material.take() {
+                Some(base) => base,
+                None => continue,
+            },
+        };
+        // Only swap when the colour actually changes, so the handle isn't needlessly marked changed.
+        if *handle != tinted {
+            *handle = tinted;
+        }
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let (a, b) = (a.as_rgba_f32(), b.as_rgba_f32());
+    Color::rgba(
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slows_stack_up_to_their_maximum() {
+        let mut effects = StatusEffects::default();
+        for _ in 0..5 {
+            effects.apply(StatusEffect::slow(1., 0.5));
+        }
+        assert_eq!(effects.speed_multiplier(), 0.125);
+
+        effects.apply(StatusEffect::stun(1.));
+        assert_eq!(effects.speed_multiplier(), 0.);
+    }
+
+    #[test]
+    fn shield_absorbs_until_spent() {
+        let mut effects = StatusEffects::default();
+        effects.apply(StatusEffect::shield(5., 2.));
+        assert_eq!(effects.absorb(1.5), 0.);
+        assert_eq!(effects.absorb(1.5), 1.);
+        assert!(!effects.has(StatusKind::Shield));
+        assert_eq!(effects.absorb(1.5), 1.5);
+    }
+}
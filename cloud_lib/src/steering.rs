@@ -0,0 +1,254 @@
+use bevy::{prelude::*, utils::HashMap};
+use std::f32::consts::PI;
+
+use crate::{
+    movement::{Locomotion, LocomotionSettings, Velocity},
+    navigation::NavGrid,
+    GameState,
+};
+
+// Directions probed for walls around each boid.
+const WALL_PROBES: usize = 8;
+
+// Weights for each steering behaviour, plus the distances they work over.
+#[derive(Resource)]
+pub struct SteeringSettings {
+    pub alignment: f32,
+    // Boids slow down over this distance as they arrive at their goal.
+    pub arrive_radius: f32,
+    pub cohesion: f32,
+    // Boids within this distance of each other flock together. Also the spatial hash cell size.
+    pub neighbour_radius: f32,
+    pub seek: f32,
+    pub separation: f32,
+    pub separation_radius: f32,
+    pub wall_avoidance: f32,
+    // How far ahead to look for walls.
+    pub wall_probe: f32,
+}
+
+impl Default for SteeringSettings {
+    fn default() -> Self {
+        Self {
+            alignment: 0.4,
+            arrive_radius: 60.,
+            cohesion: 0.3,
+            neighbour_radius: 40.,
+            seek: 1.,
+            separation: 1.6,
+            separation_radius: 16.,
+            wall_avoidance: 2.,
+            wall_probe: 24.,
+        }
+    }
+}
+
+// A member of a flock. Something else decides where it's going; the flock decides how it gets there.
+#[derive(Component, Debug, Default)]
+pub struct Boid {
+    // Which way to head, ideally already routed around walls. Zero to hold position.
+    pub heading: Vec3,
+    // Where the boid is heading, if anywhere in particular. Boids slow as they arrive.
+    pub goal: Option<Vec3>,
+    // How close to the goal counts as there.
+    pub stop_distance: f32,
+}
+
+impl Boid {
+    pub fn seek(&mut self, heading: Vec3, goal: Vec3, stop_distance: f32) {
+        self.heading = heading;
+        self.goal = Some(goal);
+        self.stop_distance = stop_distance;
+    }
+
+    pub fn wander(&mut self, heading: Vec3) {
+        self.heading = heading;
+        self.goal = None;
+    }
+}
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SteeringSettings>().add_systems(
+            Update,
+            flock
+                .before(crate::movement::locomotion)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+pub fn flock(
+    locomotion_settings: Res<LocomotionSettings>,
+    nav_grid: Res<NavGrid>,
+    mut query: Query<(Entity, &Boid, &mut Locomotion, &Transform, &Velocity)>,
+    settings: Res<SteeringSettings>,
+) {
+    // Bucket every boid by position, so each only looks at its immediate neighbours.
+    let mut buckets: HashMap<IVec2, Vec<(Entity, Vec3, Vec3)>> = HashMap::new();
+    for (entity, _, _, transform, velocity) in query.iter() {
+        buckets
+            .entry(bucket(transform.translation, settings.neighbour_radius))
+            .or_default()
+            .push((entity, transform.translation, velocity.value));
+    }
+
+    for (entity, boid, mut locomotion, transform, velocity) in query.iter_mut() {
+        let max_speed = locomotion_settings.get(locomotion.profile).max_speed;
+        let position = transform.translation;
+
+        let mut separation = Vec3::ZERO;
+        let mut heading_sum = Vec3::ZERO;
+        let mut centre_sum = Vec3::ZERO;
+        let mut neighbours = 0;
+        let cell = bucket(position, settings.neighbour_radius);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let Some(others) = buckets.get(&(cell + IVec2::new(x, y))) else {
+                    continue;
+                };
+                for (other, other_position, other_velocity) in others.iter() {
+                    if *other == entity {
+                        continue;
+                    }
+                    let away = position - *other_position;
+                    let distance = away.length();
+                    if distance > settings.neighbour_radius {
+                        continue;
+                    }
+                    if distance < settings.separation_radius && distance > 0. {
+                        separation +=
+                            away / distance * (1. - distance / settings.separation_radius);
+                    }
+                    heading_sum += *other_velocity;
+                    centre_sum += *other_position;
+                    neighbours += 1;
+                }
+            }
+        }
+
+        // Seek, easing off on arrival.
+        let arrival = boid.goal.map_or(1., |goal| {
+            let remaining = (goal - position).length() - boid.stop_distance;
+            (remaining / settings.arrive_radius).clamp(0., 1.)
+        });
+        let seek = boid.heading.normalize_or_zero() * arrival;
+
+        let (alignment, cohesion) = if neighbours > 0 {
+            let count = neighbours as f32;
+            (
+                (heading_sum / count - velocity.value) / max_speed,
+                (centre_sum / count - position) / settings.neighbour_radius,
+            )
+        } else {
+            (Vec3::ZERO, Vec3::ZERO)
+        };
+
+        // Push away from any wall within probing distance.
+        let mut avoidance = Vec3::ZERO;
+        if !nav_grid.is_empty() {
+            for i in 0..WALL_PROBES {
+                let angle = i as f32 / WALL_PROBES as f32 * 2. * PI;
+                let probe = Vec3::new(angle.cos(), angle.sin(), 0.);
+                if !nav_grid.is_walkable_at(position + probe * settings.wall_probe) {
+                    avoidance -= probe;
+                }
+            }
+        }
+
+        // Flocking only shapes the cloud; a boid with nowhere to go settles rather than drifting.
+        let flocking = if seek == Vec3::ZERO {
+            separation * settings.separation + avoidance * settings.wall_avoidance
+        } else {
+            separation * settings.separation
+                + alignment * settings.alignment
+                + cohesion * settings.cohesion
+                + avoidance * settings.wall_avoidance
+        };
+        locomotion.intent =
+            ((seek * settings.seek + flocking) * max_speed).clamp_length_max(max_speed);
+    }
+}
+
+fn bucket(position: Vec3, size: f32) -> IVec2 {
+    (position.truncate() / size).floor().as_ivec2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movement::LocomotionProfile;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<LocomotionSettings>()
+            .init_resource::<NavGrid>()
+            .init_resource::<SteeringSettings>()
+            .add_systems(Update, flock);
+        app
+    }
+
+    fn spawn_boid(app: &mut App, translation: Vec3, heading: Vec3) -> Entity {
+        app.world
+            .spawn((
+                Boid {
+                    heading,
+                    ..default()
+                },
+                Locomotion::new(LocomotionProfile::Hexling),
+                Transform::from_translation(translation),
+                Velocity::new(Vec3::ZERO),
+            ))
+            .id()
+    }
+
+    fn intent(app: &App, entity: Entity) -> Vec3 {
+        app.world.get::<Locomotion>(entity).unwrap().intent
+    }
+
+    #[test]
+    fn crowded_boids_separate() {
+        let mut app = app();
+        let a = spawn_boid(&mut app, Vec3::ZERO, Vec3::ZERO);
+        let b = spawn_boid(&mut app, Vec3::new(5., 0., 0.), Vec3::ZERO);
+        let alone = spawn_boid(&mut app, Vec3::new(500., 0., 0.), Vec3::ZERO);
+        app.update();
+
+        assert!(intent(&app, a).x < 0.);
+        assert!(intent(&app, b).x > 0.);
+        assert_eq!(intent(&app, alone), Vec3::ZERO);
+    }
+
+    #[test]
+    fn travelling_boids_draw_together() {
+        let mut app = app();
+        let separation_radius = app.world.resource::<SteeringSettings>().separation_radius;
+        // Close enough to flock, too far apart to push each other away.
+        let a = spawn_boid(&mut app, Vec3::ZERO, Vec3::Y);
+        let b = spawn_boid(&mut app, Vec3::new(separation_radius * 2., 0., 0.), Vec3::Y);
+        app.update();
+
+        assert!(intent(&app, a).x > 0.);
+        assert!(intent(&app, b).x < 0.);
+        assert!(intent(&app, a).y > 0.);
+    }
+
+    #[test]
+    fn neighbours_are_found_across_buckets() {
+        let size = SteeringSettings::default().neighbour_radius;
+        assert_eq!(bucket(Vec3::new(size - 1., 1., 0.), size), IVec2::new(0, 0));
+        assert_eq!(bucket(Vec3::new(size + 1., 1., 0.), size), IVec2::new(1, 0));
+        assert_eq!(bucket(Vec3::new(-1., -1., 0.), size), IVec2::new(-1, -1));
+
+        // Either side of a bucket boundary, these two still crowd each other.
+        let mut app = app();
+        let a = spawn_boid(&mut app, Vec3::new(size - 2., 0., 0.), Vec3::ZERO);
+        let b = spawn_boid(&mut app, Vec3::new(size + 2., 0., 0.), Vec3::ZERO);
+        app.update();
+
+        assert!(intent(&app, a).x < 0.);
+        assert!(intent(&app, b).x > 0.);
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+
+use crate::GameState;
+
+// Lines stay fully visible for this long, then fade out over `FADE_SECONDS`.
+const FADE_SECONDS: f32 = 1.5;
+const HOLD_SECONDS: f32 = 4.;
+// Older lines are dropped once this many are showing.
+const MAX_LINES: usize = 4;
+const STORY_COLOR: Color = Color::rgb(0.9, 0.95, 0.8);
+const STORY_SIZE: f32 = 18.;
+
+// A line of story text, shown at the bottom of the screen without interrupting play.
+#[derive(Event)]
+pub struct StoryEvent(pub String);
+
+#[derive(Component)]
+struct StoryLog;
+
+#[derive(Component)]
+struct StoryLine {
+    age: f32,
+}
+
+pub struct StoryPlugin;
+
+impl Plugin for StoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StoryEvent>()
+            .add_systems(Startup, spawn_log)
+            .add_systems(OnExit(GameState::Over), clear_log)
+            .add_systems(
+                Update,
+                (tell, fade_lines)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_log(mut commands: Commands) {
+    commands.spawn((
+        Name::new("story log"),
+        NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                bottom: Val::Px(24.),
+                flex_direction: FlexDirection::Column,
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                ..default()
+            },
+            ..default()
+        },
+        StoryLog,
+    ));
+}
+
+fn clear_log(mut commands: Commands, query: Query<Entity, With<StoryLine>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn tell(
+    mut commands: Commands,
+    mut ev_story: EventReader<StoryEvent>,
+    line_query: Query<(Entity, &StoryLine)>,
+    log_query: Query<Entity, With<StoryLog>>,
+) {
+    let Ok(log) = log_query.get_single() else {
+        return;
+    };
+    let mut showing: Vec<(Entity, f32)> = line_query
+        .iter()
+        .map(|(entity, line)| (entity, line.age))
+        .collect();
+    for ev in ev_story.read() {
+        let line = commands
+            .spawn((
+                StoryLine { age: 0. },
+                TextBundle::from_section(
+                    ev.0.clone(),
+                    TextStyle {
+                        color: STORY_COLOR,
+                        font_size: STORY_SIZE,
+                        ..default()
+                    },
+                ),
+            ))
+            .id();
+        commands.entity(log).add_child(line);
+        showing.push((line, 0.));
+    }
+
+    // Oldest first, so the excess comes off the front.
+    showing.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let excess = showing.len().saturating_sub(MAX_LINES);
+    for (entity, _) in showing.iter().take(excess) {
+        commands.entity(*entity).despawn_recursive();
+    }
+}
+
+fn fade_lines(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut StoryLine, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut line, mut text) in query.iter_mut() {
+        line.age += time.delta_seconds();
+        if line.age < HOLD_SECONDS {
+            continue;
+        }
+        let fade = (line.age - HOLD_SECONDS) / FADE_SECONDS;
+        if fade >= 1. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(1. - fade);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, utils::HashMap};
+use std::f32::consts::PI;
+
+use crate::{
+    combat::{Attacker, Health, Targeting},
+    damage::{DamageEvent, DamageKind, Resistances},
+    enemy::{DeathBurst, Enemy},
+    shapes::ShapeCache,
+    GameState,
+};
+
+pub const COLOR: Color = Color::rgb(0.95, 0.35, 0.0);
+// A dead swarm scatters as a spray of fine, dull embers rather than an octagon's shards.
+const DEATH_COLOR: Color = Color::rgb(0.55, 0.2, 0.0);
+const DEATH_SHARDS: usize = 40;
+// Friendlies closer than this to a member get nibbled.
+const CONTACT_RANGE: f32 = 8.;
+// Damage per second dealt by each member in contact.
+const CONTACT_DPS: f32 = 0.25;
+const COHESION: f32 = 1.5;
+const JITTER: f32 = 120.;
+const MEMBER_HEALTH: f32 = 0.2;
+const MEMBER_RADIUS: f32 = 3.;
+const MEMBER_SPEED: f32 = 90.;
+// Too many and too small to swat, but they wither in acid.
+const RESISTANCES: &[(DamageKind, f32)] =
+    &[(DamageKind::Kinetic, 0.5), (DamageKind::Corrosive, 2.)];
+const SEEK: f32 = 140.;
+const SEPARATION: f32 = 60.;
+// Members only separate from others in the same bucket; good enough for a cloud. Contact checks
+// use the same buckets.
+const SEPARATION_CELL: f32 = 8.;
+pub const SWARM_SIZE: usize = 300;
+const WANDER_RADIUS: f32 = 80.;
+
+// A swarm is a single enemy as far as targeting and combat are concerned. Its transform tracks the
+// centroid of its members, and its health is the sum of theirs: damage to the swarm thins it out.
+#[derive(Component)]
+pub struct Swarm {
+    home: Vec3,
+    members: usize,
+    wander_angle: f32,
+}
+
+// Members are purely visual, simulated in bulk by the swarm. They have no `Collider` or `Velocity`,
+// so they stay out of the per-entity collision and movement passes.
+#[derive(Component)]
+pub struct SwarmMember {
+    swarm: Entity,
+    velocity: Vec3,
+}
+
+pub struct SwarmPlugin;
+
+impl Plugin for SwarmPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_swarms)
+            .add_systems(OnExit(GameState::Over), spawn_swarms)
+            .add_systems(
+                OnEnter(GameState::Over),
+                crate::menu::despawn_thing::<SwarmMember>,
+            )
+            .add_systems(
+                Update,
+                (track_swarms, swarm_motion, swarm_contact, thin_swarms)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_swarms(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    spawn_swarm(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut shapes,
+        Vec3::new(650., 0., 0.),
+        SWARM_SIZE,
+    );
+}
+
+pub fn spawn_swarm(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    shapes: &mut ShapeCache,
+    translation: Vec3,
+    size: usize,
+) -> Entity {
+    let swarm = commands
+        .spawn((
+            // Members deal the damage, by contact, so range and rate don't apply.
+            Attacker {
+                attack_range: CONTACT_RANGE,
+                attack_rate: 0.,
+                base_damage: CONTACT_DPS,
+                cooldown: 0.,
+                damage_kind: DamageKind::Corrosive,
+            },
+            DeathBurst {
+                preset: "swarm_death",
+                color: DEATH_COLOR,
+                shards: DEATH_SHARDS,
+            },
+            Enemy,
+            Health::new(size as f32 * MEMBER_HEALTH),
+            Name::new("swarm"),
+            Resistances(RESISTANCES),
+            Targeting::new(250.),
+            Swarm {
+                home: translation,
+                members: size,
+                wander_angle: 0.,
+            },
+            TransformBundle::from_transform(Transform::from_translation(translation)),
+        ))
+        .id();
+
+    // Every member of every swarm shares one mesh and one material, so they batch together.
+    let mesh = shapes.mesh(meshes, 3, MEMBER_RADIUS);
+    let material = shapes.material(materials, COLOR);
+    commands.spawn_batch((0..size).map(move |i| {
+        // Start in a loose disc around the spawn point.
+        let angle = i as f32 * 2.4;
+        let distance = (i as f32 / size as f32).sqrt() * WANDER_RADIUS / 2.;
+        let offset = Vec3::new(angle.cos(), angle.sin(), 0.) * distance;
+        (
+            MaterialMesh2dBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(translation + offset),
+                ..default()
+            },
+            SwarmMember {
+                swarm,
+                velocity: Vec3::ZERO,
+            },
+        )
+    }));
+
+    swarm
+}
+
+// Moves each swarm's transform to the centroid of its members, and tidies up members whose swarm
+// has been destroyed.
+fn track_swarms(
+    mut commands: Commands,
+    members: Query<(Entity, &SwarmMember, &Transform), Without<Swarm>>,
+    mut swarms: Query<(&mut Swarm, &mut Transform)>,
+) {
+    let mut centroids: HashMap<Entity, (Vec3, usize)> = HashMap::new();
+    for (entity, member, transform) in members.iter() {
+        if swarms.get(member.swarm).is_err() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let entry = centroids.entry(member.swarm).or_insert((Vec3::ZERO, 0));
+        entry.0 += transform.translation;
+        entry.1 += 1;
+    }
+
+    for (swarm_entity, (sum, count)) in centroids.iter() {
+        if let Ok((mut swarm, mut transform)) = swarms.get_mut(*swarm_entity) {
+            swarm.members = *count;
+            transform.translation = *sum / *count as f32;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn swarm_motion(
+    mut members: Query<(&mut SwarmMember, &mut Transform), Without<Swarm>>,
+    mut swarms: Query<(&mut Swarm, &Targeting, &Transform)>,
+    targets: Query<&Transform, (With<Health>, Without<Enemy>, Without<SwarmMember>)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut swarm, _, _) in swarms.iter_mut() {
+        swarm.wander_angle = (swarm.wander_angle + delta * 0.5) % (2. * PI);
+    }
+
+    // Each swarm picks one goal for all of its members: its primary target, or a lazy circuit
+    // around home.
+    let mut goals: HashMap<Entity, (Vec3, Vec3)> = HashMap::new();
+    let mut buckets: HashMap<IVec2, Vec<Vec3>> = HashMap::new();
+    for (member, transform) in members.iter() {
+        let Ok((swarm, targeting, swarm_transform)) = swarms.get(member.swarm) else {
+            continue;
+        };
+        goals.entry(member.swarm).or_insert_with(|| {
+            let goal = targeting
+                .primary()
+                .and_then(|target| targets.get(target).ok())
+                .map(|target| target.translation)
+                .unwrap_or_else(|| {
+                    swarm.home
+                        + Vec3::new(swarm.wander_angle.cos(), swarm.wander_angle.sin(), 0.)
+                            * WANDER_RADIUS
+                });
+            (goal, swarm_transform.translation)
+        });
+        buckets
+            .entry(bucket(transform.translation))
+            .or_default()
+            .push(transform.translation);
+    }
+
+    for (mut member, mut transform) in members.iter_mut() {
+        let Some((goal, centroid)) = goals.get(&member.swarm) else {
+            continue;
+        };
+        let position = transform.translation;
+
+        let mut separation = Vec3::ZERO;
+        if let Some(neighbours) = buckets.get(&bucket(position)) {
+            for neighbour in neighbours.iter() {
+                let away = position - *neighbour;
+                let distance = away.length();
+                if distance > 0. {
+                    separation += away / (distance * distance);
+                }
+            }
+        }
+        let jitter = Vec3::new(rand::random::<f32>() - 0.5, rand::random::<f32>() - 0.5, 0.);
+
+        let acceleration = (*goal - position).normalize_or_zero() * SEEK
+            + (*centroid - position) * COHESION
+            + separation * SEPARATION
+            + jitter * JITTER;
+        member.velocity = (member.velocity + acceleration * delta).clamp_length_max(MEMBER_SPEED);
+        transform.translation += member.velocity * delta;
+        transform.rotation = Quat::from_rotation_z(member.velocity.y.atan2(member.velocity.x));
+    }
+}
+
+// Members nibble at any friendly they touch. Damage is totted up per friendly and swarm and applied
+// once. Members are bucketed up front, so each friendly only looks at the ones close by.
+#[allow(clippy::type_complexity)]
+fn swarm_contact(
+    mut ev_damage: EventWriter<DamageEvent>,
+    members: Query<(&SwarmMember, &Transform), Without<Swarm>>,
+    friendlies: Query<(Entity, &Transform), (With<Health>, Without<Enemy>, Without<SwarmMember>)>,
+    swarms: Query<&Attacker, With<Swarm>>,
+    time: Res<Time>,
+) {
+    let mut buckets: HashMap<IVec2, Vec<(Entity, Vec3)>> = HashMap::new();
+    for (member, transform) in members.iter() {
+        buckets
+            .entry(bucket(transform.translation))
+            .or_default()
+            .push((member.swarm, transform.translation));
+    }
+    // How many buckets out a member can be and still be in range.
+    let longest = swarms.iter().map(|a| a.attack_range).fold(0., f32::max);
+    let reach = (longest / SEPARATION_CELL).ceil() as i32;
+
+    for (friendly, friendly_transform) in friendlies.iter() {
+        let position = friendly_transform.translation;
+        let cell = bucket(position);
+        let mut touching: HashMap<Entity, usize> = HashMap::new();
+        for x in -reach..=reach {
+            for y in -reach..=reach {
+                let Some(neighbours) = buckets.get(&(cell + IVec2::new(x, y))) else {
+                    continue;
+                };
+                for (swarm, member_position) in neighbours.iter() {
+                    let Ok(attacker) = swarms.get(*swarm) else {
+                        continue;
+                    };
+                    if (*member_position - position).length() < attacker.attack_range {
+                        *touching.entry(*swarm).or_default() += 1;
+                    }
+                }
+            }
+        }
+        for (swarm, count) in touching {
+            let Ok(attacker) = swarms.get(swarm) else {
+                continue;
+            };
+            ev_damage.send(DamageEvent {
+                target: friendly,
+                source: Some(swarm),
+                amount: count as f32 * attacker.base_damage * time.delta_seconds(),
+                kind: attacker.damage_kind,
+            });
+        }
+    }
+}
+
+// Damage dealt to a swarm (by hexlings, typically) is taken out of the members closest to its
+// heart, where the hexlings are doing the fighting.
+fn thin_swarms(
+    mut commands: Commands,
+    members: Query<(Entity, &SwarmMember, &Transform), Without<Swarm>>,
+    swarms: Query<(Entity, &Swarm, &Health, &Transform)>,
+) {
+    for (swarm_entity, swarm, health, transform) in swarms.iter() {
+        let alive = (health.current / MEMBER_HEALTH).ceil().max(0.) as usize;
+        if alive >= swarm.members {
+            continue;
+        }
+
+        let mut candidates: Vec<(f32, Entity)> = members
+            .iter()
+            .filter(|(_, member, _)| member.swarm == swarm_entity)
+            .map(|(entity, _, member_transform)| {
+                (
+                    (member_transform.translation - transform.translation).length(),
+                    entity,
+                )
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, entity) in candidates.iter().take(swarm.members - alive) {
+            commands.entity(*entity).despawn_recursive();
+        }
+    }
+}
+
+fn bucket(position: Vec3) -> IVec2 {
+    (position.truncate() / SEPARATION_CELL).floor().as_ivec2()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::ManualEventReader, system::RunSystemOnce};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        damage::{apply_damage, HitEvent},
+        enemy::{splodey, Debris},
+        particles::Particles,
+        sound::SoundSettings,
+    };
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<ShapeCache>()
+            .init_resource::<Time>()
+            .add_event::<DamageEvent>()
+            .add_event::<HitEvent>();
+        app
+    }
+
+    fn spawn(app: &mut App, translation: Vec3, size: usize) -> Entity {
+        app.world.run_system_once(
+            move |mut commands: Commands,
+                  mut materials: ResMut<Assets<ColorMaterial>>,
+                  mut meshes: ResMut<Assets<Mesh>>,
+                  mut shapes: ResMut<ShapeCache>| {
+                spawn_swarm(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &mut shapes,
+                    translation,
+                    size,
+                )
+            },
+        )
+    }
+
+    fn members(app: &mut App) -> Vec<Entity> {
+        app.world
+            .query_filtered::<Entity, With<SwarmMember>>()
+            .iter(&app.world)
+            .collect()
+    }
+
+    #[test]
+    fn damage_thins_out_members() {
+        let mut app = app();
+        app.add_systems(Update, (apply_damage, thin_swarms).chain());
+        let swarm = spawn(&mut app, Vec3::ZERO, 10);
+        // Halved by the swarm's resistance to kinetic damage: 0.7 is three and a half members' worth.
+        app.world.send_event(DamageEvent {
+            target: swarm,
+            source: None,
+            amount: 1.4,
+            kind: DamageKind::Kinetic,
+        });
+        app.update();
+
+        assert_eq!(members(&mut app).len(), 7);
+    }
+
+    #[test]
+    fn contact_damage_scales_with_members_in_reach() {
+        let mut app = app();
+        app.add_systems(Update, swarm_contact);
+        spawn(&mut app, Vec3::ZERO, 4);
+        let crowded = app
+            .world
+            .spawn((Health::new(1.), Transform::default()))
+            .id();
+        let grazed = app
+            .world
+            .spawn((
+                Health::new(1.),
+                Transform::from_translation(Vec3::new(100., 0., 0.)),
+            ))
+            .id();
+        let offsets = [Vec3::X, Vec3::Y, Vec3::NEG_X, Vec3::new(101., 0., 0.)];
+        for (member, offset) in members(&mut app).into_iter().zip(offsets) {
+            app.world.get_mut::<Transform>(member).unwrap().translation = offset;
+        }
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+
+        let events = app.world.resource::<Events<DamageEvent>>();
+        let damage_to = |target: Entity| -> f32 {
+            ManualEventReader::<DamageEvent>::default()
+                .read(events)
+                .filter(|ev| ev.target == target)
+                .map(|ev| ev.amount)
+                .sum()
+        };
+        assert_eq!(damage_to(crowded), 3. * CONTACT_DPS);
+        assert_eq!(damage_to(grazed), CONTACT_DPS);
+    }
+
+    #[test]
+    fn a_dead_swarm_takes_its_members_with_it() {
+        let mut app = app();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .init_resource::<Particles>()
+            .insert_resource(SoundSettings {
+                effects_on: true,
+                effects_volume: 0.5,
+                global_sound_on: true,
+                global_volume_db: 1.,
+                soundtrack_on: true,
+                soundtrack_volume: 1.,
+            })
+            .add_systems(
+                Update,
+                (splodey, apply_deferred, track_swarms, apply_deferred).chain(),
+            );
+        let swarm = spawn(&mut app, Vec3::ZERO, 10);
+        app.world.get_mut::<Health>(swarm).unwrap().current = 0.;
+        app.update();
+
+        assert!(app.world.get_entity(swarm).is_none());
+        assert!(members(&mut app).is_empty());
+        let shards: Vec<Color> = app
+            .world
+            .query_filtered::<&Handle<ColorMaterial>, With<Debris>>()
+            .iter(&app.world)
+            .map(|handle| {
+                app.world
+                    .resource::<Assets<ColorMaterial>>()
+                    .get(handle)
+                    .unwrap()
+                    .color
+            })
+            .collect();
+        assert_eq!(shards.len(), DEATH_SHARDS);
+        assert!(shards.iter().all(|color| *color != crate::enemy::COLOR));
+    }
+}
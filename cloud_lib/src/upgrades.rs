@@ -0,0 +1,402 @@
+use bevy::{prelude::*, utils::HashSet};
+use serde::Deserialize;
+
+use crate::{
+    economy::Stockpile,
+    hexling::Hexling,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    pause_menu::PauseScreen,
+    status::{ApplyStatusEvent, StatusEffect, TrapSense},
+    GameState,
+};
+
+const AVAILABLE_COLOR: Color = Color::WHITE;
+const LOCKED_COLOR: Color = Color::rgba(1., 1., 1., 0.35);
+const TREE: &str = include_str!("../data/upgrades.ron");
+const UNLOCKED_COLOR: Color = Color::rgb(0.4, 1., 0.5);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum UpgradeEffect {
+    Modify { stat: Stat, op: ModifierOp },
+    MaxHealth(f32),
+    // A fresh shield of `absorb` every `every` seconds.
+    Shield { absorb: f32, every: f32 },
+    // Traps no longer spring on hexlings.
+    DetectTraps,
+    // Room for this many more hexlings.
+    Population(usize),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpgradeNode {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // In matter.
+    pub cost: f32,
+    pub requires: Vec<&'static str>,
+    pub effects: Vec<UpgradeEffect>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NodeStatus {
+    Unlocked,
+    Available,
+    Locked,
+}
+
+// The tree, as read from `data/upgrades.ron`, and which of it has been bought this run.
+#[derive(Resource)]
+pub struct Upgrades {
+    pub tree: Vec<UpgradeNode>,
+    pub unlocked: HashSet<&'static str>,
+}
+
+impl Default for Upgrades {
+    fn default() -> Self {
+        Self {
+            tree: ron::from_str(TREE).expect("upgrade tree should be valid"),
+            unlocked: HashSet::new(),
+        }
+    }
+}
+
+impl Upgrades {
+    pub fn status(&self, node: &UpgradeNode) -> NodeStatus {
+        if self.unlocked.contains(node.id) {
+            NodeStatus::Unlocked
+        } else if node.requires.iter().all(|id| self.unlocked.contains(id)) {
+            NodeStatus::Available
+        } else {
+            NodeStatus::Locked
+        }
+    }
+
+    // Spends the matter and unlocks the node, if it's available and affordable.
+    pub fn buy(&mut self, index: usize, stockpile: &mut Stockpile) -> bool {
+        let Some(node) = self.tree.get(index) else {
+            return false;
+        };
+        if self.status(node) != NodeStatus::Available || stockpile.matter < node.cost {
+            return false;
+        }
+        stockpile.matter -= node.cost;
+        self.unlocked.insert(node.id);
+        true
+    }
+
+    // Extra room for hexlings from everything bought so far.
+    pub fn population_bonus(&self) -> usize {
+        self.tree
+            .iter()
+            .filter(|node| self.unlocked.contains(node.id))
+            .flat_map(|node| node.effects.iter())
+            .map(|effect| match effect {
+                UpgradeEffect::Population(extra) => *extra,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // How far down the tree a node sits, for indenting.
+    fn depth(&self, node: &UpgradeNode) -> usize {
+        node.requires
+            .iter()
+            .filter_map(|id| self.tree.iter().find(|n| n.id == *id))
+            .map(|parent| self.depth(parent) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// Which upgrades a hexling has had applied already.
+#[derive(Component, Debug, Default)]
+pub struct Upgraded(HashSet<&'static str>);
+
+#[derive(Component, Debug)]
+pub struct ShieldRegrowth {
+    absorb: f32,
+    every: f32,
+    timer: f32,
+}
+
+#[derive(Component)]
+struct UpgradeScreen;
+
+#[derive(Component)]
+struct UpgradeLine(usize);
+
+#[derive(Component)]
+struct MatterLine;
+
+// The node highlighted on the upgrade screen.
+#[derive(Resource, Default)]
+struct UpgradeCursor(usize);
+
+pub struct UpgradePlugin;
+
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Upgrades>()
+            .init_resource::<UpgradeCursor>()
+            .add_systems(OnExit(GameState::Over), reset_upgrades)
+            .add_systems(OnEnter(PauseScreen::Upgrades), spawn_screen)
+            .add_systems(
+                OnExit(PauseScreen::Upgrades),
+                crate::menu::despawn_thing::<UpgradeScreen>,
+            )
+            .add_systems(
+                Update,
+                (upgrade_controls, refresh_screen)
+                    .chain()
+                    .run_if(in_state(PauseScreen::Upgrades)),
+            )
+            .add_systems(
+                Update,
+                (apply_upgrades, regrow_shields)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn reset_upgrades(mut upgrades: ResMut<Upgrades>) {
+    upgrades.unlocked.clear();
+}
+
+fn apply_upgrades(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut StatModifiers, Option<&mut Upgraded>), With<Hexling>>,
+    upgrades: Res<Upgrades>,
+) {
+    for (entity, mut modifiers, upgraded) in query.iter_mut() {
+        let applied = upgraded.as_ref().map_or(0, |u| u.0.len());
+        if applied == upgrades.unlocked.len() {
+            continue;
+        }
+        let mut done = upgraded.as_ref().map_or_else(HashSet::new, |u| u.0.clone());
+        for node in upgrades.tree.iter() {
+            if !upgrades.unlocked.contains(node.id) || !done.insert(node.id) {
+                continue;
+            }
+            for effect in node.effects.iter() {
+                match *effect {
+                    UpgradeEffect::Modify { stat, op } => {
+                        modifiers.add(StatModifier::new(stat, op, node.id));
+                    }
+                    UpgradeEffect::MaxHealth(amount) => {
+                        modifiers.add(StatModifier::new(
+                            Stat::MaxHealth,
+                            ModifierOp::Add(amount),
+                            node.id,
+                        ));
+                    }
+                    UpgradeEffect::Shield { absorb, every } => {
+                        commands.entity(entity).insert(ShieldRegrowth {
+                            absorb,
+                            every,
+                            timer: 0.,
+                        });
+                    }
+                    UpgradeEffect::DetectTraps => {
+                        commands.entity(entity).insert(TrapSense);
+                    }
+                    // Not for any one hexling; see `population_bonus`.
+                    UpgradeEffect::Population(_) => {}
+                }
+            }
+        }
+        match upgraded {
+            Some(mut upgraded) => upgraded.0 = done,
+            None => {
+                commands.entity(entity).insert(Upgraded(done));
+            }
+        }
+    }
+}
+
+fn regrow_shields(
+    mut ev_apply_status: EventWriter<ApplyStatusEvent>,
+    mut query: Query<(Entity, &mut ShieldRegrowth)>,
+    time: Res<Time>,
+) {
+    for (entity, mut regrowth) in query.iter_mut() {
+        regrowth.timer -= time.delta_seconds();
+        if regrowth.timer > 0. {
+            continue;
+        }
+        regrowth.timer = regrowth.every;
+        ev_apply_status.send(ApplyStatusEvent {
+            target: entity,
+            effect: StatusEffect {
+                max_stacks: 1,
+                ..StatusEffect::shield(regrowth.every, regrowth.absorb)
+            },
+        });
+    }
+}
+
+fn spawn_screen(
+    mut commands: Commands,
+    mut cursor: ResMut<UpgradeCursor>,
+    upgrades: Res<Upgrades>,
+) {
+    cursor.0 = 0;
+    let text = |value: &str, font_size: f32| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font_size,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    width: Val::Percent(100.),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+                ..Default::default()
+            },
+            UpgradeScreen,
+        ))
+        .with_children(|builder| {
+            builder.spawn(text("upgrades", 36.));
+            builder.spawn((MatterLine, text("", 20.)));
+            builder
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        margin: UiRect::vertical(Val::Px(16.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    for i in 0..upgrades.tree.len() {
+                        builder.spawn((UpgradeLine(i), text("", 18.)));
+                    }
+                });
+            builder.spawn(text("up/down to choose, enter to buy, esc to go back", 16.));
+        });
+}
+
+fn upgrade_controls(
+    mut cursor: ResMut<UpgradeCursor>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_screen: ResMut<NextState<PauseScreen>>,
+    mut stockpile: ResMut<Stockpile>,
+    mut upgrades: ResMut<Upgrades>,
+) {
+    let count = upgrades.tree.len();
+    if count == 0 {
+        return;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W]) {
+        cursor.0 = (cursor.0 + count - 1) % count;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S]) {
+        cursor.0 = (cursor.0 + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        upgrades.buy(cursor.0, &mut stockpile);
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_screen.set(PauseScreen::Menu);
+    }
+}
+
+fn refresh_screen(
+    cursor: Res<UpgradeCursor>,
+    mut line_query: Query<(&UpgradeLine, &mut Text), Without<MatterLine>>,
+    mut matter_query: Query<&mut Text, With<MatterLine>>,
+    stockpile: Res<Stockpile>,
+    upgrades: Res<Upgrades>,
+) {
+    if let Ok(mut text) = matter_query.get_single_mut() {
+        let value = format!("matter: {}", stockpile.matter.floor());
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+    for (line, mut text) in line_query.iter_mut() {
+        let node = &upgrades.tree[line.0];
+        let status = upgrades.status(node);
+        let (mark, color) = match status {
+            NodeStatus::Unlocked => ("x", UNLOCKED_COLOR),
+            NodeStatus::Available => (" ", AVAILABLE_COLOR),
+            NodeStatus::Locked => ("-", LOCKED_COLOR),
+        };
+        let value = format!(
+            "{}{}[{}] {} ({}): {}",
+            if cursor.0 == line.0 { "> " } else { "  " },
+            "    ".repeat(upgrades.depth(node)),
+            mark,
+            node.name,
+            node.cost,
+            node.description,
+        );
+        // Only touch the text when it changes, to save re-laying it out every frame.
+        if text.sections[0].value != value || text.sections[0].style.color != color {
+            let section = &mut text.sections[0];
+            section.value = value;
+            section.style.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_is_well_formed() {
+        let upgrades = Upgrades::default();
+        assert!(!upgrades.tree.is_empty());
+        for (i, node) in upgrades.tree.iter().enumerate() {
+            assert!(
+                upgrades.tree[..i].iter().all(|other| other.id != node.id),
+                "duplicate upgrade {}",
+                node.id
+            );
+            for id in node.requires.iter() {
+                assert!(
+                    upgrades.tree.iter().any(|other| other.id == *id),
+                    "{} requires unknown upgrade {}",
+                    node.id,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn buying_needs_matter_and_prerequisites() {
+        let mut upgrades = Upgrades::default();
+        let index =
+            |upgrades: &Upgrades, id| upgrades.tree.iter().position(|n| n.id == id).unwrap();
+        let root = index(&upgrades, "sharp_edges");
+        let child = index(&upgrades, "extra_firepower");
+        let mut stockpile = Stockpile { matter: 100. };
+
+        assert!(!upgrades.buy(child, &mut stockpile));
+        assert!(upgrades.buy(root, &mut stockpile));
+        assert!(!upgrades.buy(root, &mut stockpile));
+        assert!(upgrades.buy(child, &mut stockpile));
+        let spent = upgrades.tree[root].cost + upgrades.tree[child].cost;
+        assert_eq!(stockpile.matter, 100. - spent);
+
+        let mut broke = Stockpile { matter: 0. };
+        let other = index(&upgrades, "thick_skin");
+        assert!(!upgrades.buy(other, &mut broke));
+    }
+}
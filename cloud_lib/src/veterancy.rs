@@ -0,0 +1,338 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, utils::HashSet};
+
+use crate::{
+    classes::HexlingClass,
+    combat::Health,
+    damage::HitEvent,
+    enemy::Enemy,
+    feedback::Flash,
+    hexling::Hexling,
+    modifiers::{ModifierOp, Stat, StatModifier, StatModifiers},
+    shapes::ShapeCache,
+    status::StatusEffects,
+    story::StoryEvent,
+    GameState,
+};
+
+// Veterans shine this much brighter than they were born.
+const BRIGHTEN: f32 = 1.6;
+const DAMAGE_PER_LEVEL: f32 = 0.1;
+const HEALTH_PER_LEVEL: f32 = 1.;
+const KILL_XP: f32 = 5.;
+const MAX_RINGS: u32 = 3;
+const NAME_ENDINGS: [&str; 8] = ["a", "ek", "ix", "o", "ra", "ric", "sel", "un"];
+const NAME_STARTS: [&str; 10] = [
+    "Ash", "Bri", "Cor", "Dax", "Hex", "Kel", "Mor", "Pip", "Tav", "Zan",
+];
+const RING_COLOR: Color = Color::rgb(2.2, 1.8, 0.6);
+const RING_GAP_COLOR: Color = Color::rgb(0.05, 0.05, 0.05);
+const RING_WIDTH: f32 = 1.5;
+// Hexlings earn a name at this level.
+const VETERAN_LEVEL: u32 = 3;
+// Experience needed for each level grows with the level: 5, 20, 45, 80...
+const XP_PER_LEVEL: f32 = 5.;
+
+#[derive(Component, Debug, Default)]
+pub struct Veterancy {
+    pub kills: u32,
+    pub damage_dealt: f32,
+    pub level: u32,
+}
+
+impl Veterancy {
+    pub fn experience(&self) -> f32 {
+        self.damage_dealt + self.kills as f32 * KILL_XP
+    }
+
+    // The level the experience so far is worth.
+    fn earned_level(&self) -> u32 {
+        let mut level = self.level;
+        while self.experience() >= xp_for_level(level + 1) {
+            level += 1;
+        }
+        level
+    }
+}
+
+fn xp_for_level(level: u32) -> f32 {
+    XP_PER_LEVEL * (level * level) as f32
+}
+
+// A hexling that has been around long enough to earn a name.
+#[derive(Component, Debug)]
+pub struct Veteran {
+    pub name: String,
+}
+
+#[derive(Component)]
+struct VeteranRing;
+
+pub struct VeterancyPlugin;
+
+impl Plugin for VeterancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (credit_hits, level_up, promote)
+                    .chain()
+                    .after(crate::damage::apply_damage),
+                mourn_veterans
+                    .after(crate::damage::apply_damage)
+                    .before(crate::hexling::hexling_deaths),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn credit_hits(
+    enemy_query: Query<&Health, With<Enemy>>,
+    mut ev_hit: EventReader<HitEvent>,
+    mut query: Query<&mut Veterancy>,
+) {
+    // Several hits can land on an enemy in the frame it dies; only the first gets the kill.
+    let mut killed = HashSet::new();
+    for ev in ev_hit.read() {
+        let Some(Ok(mut veterancy)) = ev.source.map(|source| query.get_mut(source)) else {
+            continue;
+        };
+        let Ok(health) = enemy_query.get(ev.target) else {
+            continue;
+        };
+        veterancy.damage_dealt += ev.amount;
+        if health.is_dead() && killed.insert(ev.target) {
+            veterancy.kills += 1;
+        }
+    }
+}
+
+// The extra health arrives filled once the modifiers are next applied.
+fn level_up(mut query: Query<(&mut StatModifiers, &mut Veterancy)>) {
+    for (mut modifiers, mut veterancy) in query.iter_mut() {
+        let level = veterancy.earned_level();
+        if level == veterancy.level {
+            continue;
+        }
+        veterancy.level = level;
+        modifiers.add(StatModifier::new(
+            Stat::MaxHealth,
+            ModifierOp::Add(HEALTH_PER_LEVEL * level as f32),
+            "veterancy",
+        ));
+        modifiers.add(StatModifier::new(
+            Stat::BaseDamage,
+            ModifierOp::Mul(1. + DAMAGE_PER_LEVEL * level as f32),
+            "veterancy",
+        ));
+    }
+}
+
+// Names, brightens and rings hexlings as they reach veteran levels.
+#[allow(clippy::type_complexity)]
+fn promote(
+    mut commands: Commands,
+    mut ev_story: EventWriter<StoryEvent>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<
+        (
+            Entity,
+            Option<&HexlingClass>,
+            Option<&mut Flash>,
+            &mut Handle<ColorMaterial>,
+            Option<&Children>,
+            Option<&mut StatusEffects>,
+            &Veterancy,
+            Option<&Veteran>,
+        ),
+        (With<Hexling>, Changed<Veterancy>),
+    >,
+    ring_query: Query<(), With<VeteranRing>>,
+    mut shapes: ResMut<ShapeCache>,
+) {
+    for (entity, class, flash, mut handle, children, effects, veterancy, veteran) in
+        query.iter_mut()
+    {
+        if veterancy.level < VETERAN_LEVEL {
+            continue;
+        }
+        let rings = (veterancy.level - VETERAN_LEVEL + 1).min(MAX_RINGS);
+        let existing =
+            children.map_or(0, |c| c.iter().filter(|c| ring_query.contains(**c)).count());
+        if existing as u32 >= rings {
+            continue;
+        }
+
+        if veteran.is_none() {
+            let class = class.map_or("hexling", |c| c.stats().name);
+            let name = format!("{} the {}", generate_name(), class);
+            ev_story.send(StoryEvent(format!("{} has earned a name.", name)));
+            // Brighten the hexling's own colour, not whatever it's flashing or tinted.
+            let base = effects.and_then(|e| e.into_inner().base_material_mut());
+            let own = match (&base, &flash) {
+                (Some(base), _) => &**base,
+                (None, Some(flash)) => &flash.restore,
+                (None, None) => &*handle,
+            };
+            if let Some(color) = materials.get(own).map(|m| m.color) {
+                let brightened = shapes.material(&mut materials, brighten(color));
+                // A tint is worked out from the base, so it picks the new colour up by itself.
+                match (base, flash) {
+                    (Some(base), _) => *base = brightened,
+                    (None, Some(mut flash)) => flash.restore = brightened,
+                    (None, None) => *handle = brightened,
+                }
+            }
+            commands
+                .entity(entity)
+                .insert((Name::new(name.clone()), Veteran { name }));
+        }
+
+        // Alternating bands behind the body read as rings; each new one goes round the outside.
+        let radius = class.map_or(6., |c| c.stats().radius);
+        for i in existing as u32..rings {
+            let outer = radius + RING_WIDTH * (2 * i + 2) as f32;
+            let gap = radius + RING_WIDTH * (2 * i + 1) as f32;
+            for (size, color, depth) in [
+                (outer, RING_COLOR, 2 * i + 2),
+                (gap, RING_GAP_COLOR, 2 * i + 1),
+            ] {
+                let ring = commands
+                    .spawn((
+                        MaterialMesh2dBundle {
+                            mesh: shapes.mesh(&mut meshes, 6, size),
+                            material: shapes.material(&mut materials, color),
+                            transform: Transform::from_xyz(0., 0., -0.01 * depth as f32),
+                            ..default()
+                        },
+                        VeteranRing,
+                    ))
+                    .id();
+                commands.entity(entity).add_child(ring);
+            }
+        }
+    }
+}
+
+// Veterans get a line in the story when they fall. Runs before their bodies are cleared away.
+fn mourn_veterans(
+    mut ev_story: EventWriter<StoryEvent>,
+    query: Query<(&Health, &Veteran, &Veterancy)>,
+) {
+    for (health, veteran, veterancy) in query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+        let kills = match veterancy.kills {
+            0 => "no kills to their name".to_string(),
+            1 => "one kill to their name".to_string(),
+            n => format!("{} kills to their name", n),
+        };
+        ev_story.send(StoryEvent(format!(
+            "{} fell at level {}, with {}.",
+            veteran.name, veterancy.level, kills
+        )));
+    }
+}
+
+fn generate_name() -> String {
+    let start = NAME_STARTS[rand::random::<usize>() % NAME_STARTS.len()];
+    let end = NAME_ENDINGS[rand::random::<usize>() % NAME_ENDINGS.len()];
+    format!("{}{}", start, end)
+}
+
+fn brighten(color: Color) -> Color {
+    Color::rgba(
+        color.r() * BRIGHTEN,
+        color.g() * BRIGHTEN,
+        color.b() * BRIGHTEN,
+        color.a(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use super::*;
+    use crate::damage::DamageKind;
+
+    #[test]
+    fn levels_follow_experience() {
+        let mut veterancy = Veterancy::default();
+        assert_eq!(veterancy.earned_level(), 0);
+        veterancy.damage_dealt = 4.;
+        assert_eq!(veterancy.earned_level(), 0);
+        veterancy.kills = 1;
+        assert_eq!(veterancy.earned_level(), 1);
+        // 45 experience is exactly level 3.
+        veterancy.kills = 8;
+        veterancy.damage_dealt = 5.;
+        assert_eq!(veterancy.earned_level(), VETERAN_LEVEL);
+    }
+
+    #[test]
+    fn a_kill_is_credited_once() {
+        let mut app = App::new();
+        app.add_event::<HitEvent>().add_systems(Update, credit_hits);
+        let mut health = Health::new(1.);
+        health.current = 0.;
+        let enemy = app.world.spawn((Enemy, health)).id();
+        let first = app.world.spawn(Veterancy::default()).id();
+        let second = app.world.spawn(Veterancy::default()).id();
+        // The killing blow, and a couple more that landed the same frame.
+        for source in [first, second, first] {
+            app.world.send_event(HitEvent {
+                target: enemy,
+                source: Some(source),
+                amount: 1.,
+                kind: DamageKind::Kinetic,
+            });
+        }
+        app.update();
+
+        let first = app.world.get::<Veterancy>(first).unwrap();
+        let second = app.world.get::<Veterancy>(second).unwrap();
+        assert_eq!(first.kills + second.kills, 1);
+        assert_eq!(first.kills, 1);
+        assert_eq!(first.damage_dealt, 2.);
+        assert_eq!(second.damage_dealt, 1.);
+    }
+
+    #[test]
+    fn fallen_veterans_are_remembered() {
+        let mut app = App::new();
+        app.add_event::<StoryEvent>()
+            .add_systems(Update, mourn_veterans);
+        let veteran = |app: &mut App, name: &str| {
+            app.world
+                .spawn((
+                    Health::new(10.),
+                    Veteran {
+                        name: name.to_string(),
+                    },
+                    Veterancy {
+                        kills: 9,
+                        damage_dealt: 0.,
+                        level: VETERAN_LEVEL,
+                    },
+                ))
+                .id()
+        };
+        let fallen = veteran(&mut app, "Hexa");
+        veteran(&mut app, "Pipo");
+        app.world.get_mut::<Health>(fallen).unwrap().current = 0.;
+        app.update();
+
+        let events = app.world.resource::<Events<StoryEvent>>();
+        let story: Vec<String> = ManualEventReader::<StoryEvent>::default()
+            .read(events)
+            .map(|ev| ev.0.clone())
+            .collect();
+        assert_eq!(
+            story,
+            vec!["Hexa fell at level 3, with 9 kills to their name.".to_string()]
+        );
+    }
+}